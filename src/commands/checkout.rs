@@ -1,19 +1,27 @@
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
 use core::context::Context;
 use utils::error::Error;
 
-pub fn exec(context: &mut Context, args: &mut Vec<String>) -> Result<(), Error> {
-    let branch_name = match args.get(0) {
-        Some(b) => b,
-        None => {
-            let error = format!(
-                "\
-'checkout' requires BRANCH argument.
-Usage: bog checkout BRANCH.
-                "
-            );
-            return Err(error)?;
-        }
-    };
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("checkout")
+        .about("Checkout a local or remote branch, create it if it does not exist, and copy its files to local")
+        .arg(Arg::with_name("BRANCH")
+            .required(true)
+            .help("Name of the branch, it can not start with an underscore"))
+        .arg(Arg::with_name("from")
+            .long("from")
+            .value_name("BASE")
+            .help("Branch to create the new branch from [default: current branch]"))
+        .arg(Arg::with_name("remote")
+            .long("remote")
+            .value_name("NAME")
+            .help("Remote to fetch and push [default: remote tracked by the branch or in config]"))
+}
 
-    context.repository().checkout(branch_name)
-}
\ No newline at end of file
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    let branch_name = matches.value_of("BRANCH").unwrap_or_default();
+    context.repository().checkout(branch_name, matches.value_of("from"), matches.value_of("remote"))
+}
@@ -0,0 +1,25 @@
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
+use core::context::Context;
+use std::path::Path;
+use utils::error::Error;
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("checkout-file")
+        .about("Backup the local files and write the version of a file at a revision or date of the working branch")
+        .arg(Arg::with_name("PATH")
+            .required(true)
+            .help("File or dir inside a section, relative to the home dir"))
+        .arg(Arg::with_name("at")
+            .long("at")
+            .value_name("REV|DATE")
+            .required(true)
+            .help("Commit, branch or revision like HEAD~2, or date like 2026-10-18 to take the last commit before it"))
+}
+
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    let path = Path::new(matches.value_of("PATH").unwrap_or_default());
+    context.repository().checkout_file(path, matches.value_of("at").unwrap_or_default())
+}
@@ -1,19 +1,30 @@
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
 use core::context::Context;
 use utils::error::Error;
 
-pub fn exec(context: &mut Context, args: &mut Vec<String>) -> Result<(), Error> {
-    let repo = match args.first() {
-        None => {
-            let error = format!(
-                "\
-'clone' requires REPO argument.
-Usage: scog clone REPO.
-                "
-            );
-            return Err(error)?;
-        }
-        Some(repo) => repo,
-    };
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("clone")
+        .about("Clone the remote repository in the local repository path")
+        .arg(Arg::with_name("REPO")
+            .required(true)
+            .help("Url of the remote repository"))
+        .arg(Arg::with_name("branch")
+            .long("branch")
+            .value_name("NAME")
+            .help("Branch to checkout, a local branch tracking the remote one is created"))
+        .arg(Arg::with_name("apply")
+            .long("apply")
+            .help("Backup existing local files in a backup branch and copy the branch files to local"))
+        .arg(Arg::with_name("preview")
+            .long("preview")
+            .requires("apply")
+            .help("Only show the existing local files that would be replaced by --apply"))
+}
 
-    context.repository().clone(repo.as_str())
-}
\ No newline at end of file
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    let repo = matches.value_of("REPO").unwrap_or_default();
+    context.repository().clone(repo, matches.value_of("branch"), matches.is_present("apply"), matches.is_present("preview"))
+}
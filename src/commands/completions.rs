@@ -0,0 +1,30 @@
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::Shell;
+use clap::SubCommand;
+use commands;
+use core::context::Context;
+use std::io;
+use utils::error::Error;
+use utils::error::Kind;
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("completions")
+        .about("Print the completion script for the passed shell")
+        .arg(Arg::with_name("SHELL")
+            .required(true)
+            .possible_values(&["bash", "zsh", "fish"])
+            .help("Shell to generate the script for"))
+}
+
+pub fn exec(_: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    let shell = match matches.value_of("SHELL") {
+        Some("bash") => Shell::Bash,
+        Some("zsh") => Shell::Zsh,
+        Some("fish") => Shell::Fish,
+        shell => return Err(Error::new(Kind::Usage, format!("'{}' is not a supported shell.", shell.unwrap_or_default()))),
+    };
+    commands::app().gen_completions_to("scog", shell, &mut io::stdout());
+    Ok(())
+}
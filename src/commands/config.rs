@@ -0,0 +1,36 @@
+use clap::App;
+use clap::AppSettings;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
+use core::config::Format;
+use core::context::Context;
+use utils::error::Error;
+use utils::error::Kind;
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("config")
+        .about("Check and convert the config of the working branch")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("check")
+            .about("Report unknown keys and invalid, duplicate or overlapping paths with their line and column"))
+        .subcommand(SubCommand::with_name("convert")
+            .about("Rewrite the config in another format and commit it in place of the current one, comments are dropped")
+            .arg(Arg::with_name("to")
+                .long("to")
+                .value_name("FORMAT")
+                .required(true)
+                .possible_values(&["yaml", "toml", "json"])
+                .help("Format of the new config file")))
+}
+
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    match matches.subcommand() {
+        ("check", Some(_)) => context.repository().config_check(),
+        ("convert", Some(matches)) => {
+            let to = matches.value_of("to").and_then(Format::from_extension).unwrap_or(Format::Yaml);
+            context.repository().config_convert(to)
+        }
+        (cmd, _) => Err(Error::new(Kind::Usage, format!("'{}' is not a valid config COMMAND.", cmd))),
+    }
+}
@@ -0,0 +1,27 @@
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
+use core::context::Context;
+use std::path::PathBuf;
+use utils::error::Error;
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("init")
+        .about("Create a new repository in the local repository path")
+        .arg(Arg::with_name("remote")
+            .long("remote")
+            .value_name("URL")
+            .help("Remote repository to push the new branch to and track"))
+        .arg(Arg::with_name("PATH")
+            .multiple(true)
+            .help("Files or dirs to add to the config, relative to the home dir"))
+}
+
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    let paths: Vec<PathBuf> = match matches.values_of("PATH") {
+        Some(paths) => paths.map(PathBuf::from).collect(),
+        None => vec![],
+    };
+    context.repository().init(matches.value_of("remote"), &paths)
+}
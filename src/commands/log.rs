@@ -0,0 +1,39 @@
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
+use core::context::Context;
+use std::path::Path;
+use utils::error::Error;
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("log")
+        .about("Show the commits of the working branch, optionally only the ones changing a path")
+        .arg(Arg::with_name("PATH")
+            .help("File or dir to show the history of, relative to the home dir"))
+        .arg(Arg::with_name("backups")
+            .long("backups")
+            .help("Include the backup branches of the working branch"))
+        .arg(Arg::with_name("patch")
+            .long("patch")
+            .short("p")
+            .help("Show the diff of each commit"))
+        .arg(Arg::with_name("since")
+            .long("since")
+            .value_name("DATE")
+            .help("Show only commits after the date, like 2026-10-18 or 2026-10-18 10:00"))
+        .arg(Arg::with_name("until")
+            .long("until")
+            .value_name("DATE")
+            .help("Show only commits before the date, like 2026-10-18 or 2026-10-18 10:00"))
+}
+
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    context.repository().log(
+        matches.value_of("PATH").map(Path::new),
+        matches.is_present("backups"),
+        matches.value_of("since"),
+        matches.value_of("until"),
+        matches.is_present("patch"),
+    )
+}
@@ -1,20 +1,107 @@
+use clap::App;
+use clap::AppSettings;
+use clap::Arg;
+use clap::ArgMatches;
 use core::context::Context;
 use utils::error::Error;
+use utils::error::Kind;
+use utils::output::Output;
+use utils::output::Verbosity;
+use std::path::PathBuf;
 
 pub mod clone;
 pub mod checkout;
-pub mod help;
+pub mod checkout_file;
+pub mod completions;
+pub mod config;
+pub mod init;
+pub mod log;
+pub mod profile;
 pub mod pull;
 pub mod push;
+pub mod remote;
+pub mod status;
+pub mod sync;
+pub mod undo;
 
-pub fn exec(cmd: &str) -> Option<fn(&mut Context, &mut Vec<String>) -> Result<(), Error>> {
-    let f = match cmd {
-        "clone" => clone::exec,
-        "checkout" => checkout::exec,
-        "help" => help::exec,
-        "pull" => pull::exec,
-        "push" => push::exec,
-        _ => return None,
+/// Definition of all commands and global options
+pub fn app<'a, 'b>() -> App<'a, 'b> {
+    App::new("scog")
+        .about("Sync Config Over Git")
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .setting(AppSettings::VersionlessSubcommands)
+        .arg(Arg::with_name("verbose")
+            .long("verbose")
+            .short("v")
+            .global(true)
+            .conflicts_with("quiet")
+            .help("Print every copied file"))
+        .arg(Arg::with_name("quiet")
+            .long("quiet")
+            .short("q")
+            .global(true)
+            .help("Print only errors"))
+        .arg(Arg::with_name("repo")
+            .long("repo")
+            .value_name("PATH")
+            .global(true)
+            .help("Path of the local repository [default: $HOME/.scog]"))
+        .arg(Arg::with_name("output")
+            .long("output")
+            .value_name("FORMAT")
+            .possible_values(&["human", "json"])
+            .global(true)
+            .help("Format of the result printed on stdout [default: human]"))
+        .subcommand(clone::command())
+        .subcommand(checkout::command())
+        .subcommand(checkout_file::command())
+        .subcommand(completions::command())
+        .subcommand(config::command())
+        .subcommand(init::command())
+        .subcommand(log::command())
+        .subcommand(profile::command())
+        .subcommand(pull::command())
+        .subcommand(push::command())
+        .subcommand(remote::command())
+        .subcommand(status::command())
+        .subcommand(sync::command())
+        .subcommand(undo::command())
+}
+
+/// Create the context from the global options
+pub fn context(matches: &ArgMatches) -> Context {
+    let output = match matches.value_of("output") {
+        Some("json") => Output::Json,
+        _ => Output::Human,
+    };
+    let verbosity = if matches.is_present("quiet") {
+        Verbosity::Quiet
+    } else if matches.is_present("verbose") {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
     };
-    Some(f)
-}
\ No newline at end of file
+    let repository_dir = matches.value_of("repo").map(PathBuf::from);
+    Context::new(output, verbosity, repository_dir)
+}
+
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    match matches.subcommand() {
+        ("clone", Some(matches)) => clone::exec(context, matches),
+        ("checkout", Some(matches)) => checkout::exec(context, matches),
+        ("checkout-file", Some(matches)) => checkout_file::exec(context, matches),
+        ("completions", Some(matches)) => completions::exec(context, matches),
+        ("config", Some(matches)) => config::exec(context, matches),
+        ("init", Some(matches)) => init::exec(context, matches),
+        ("log", Some(matches)) => log::exec(context, matches),
+        ("profile", Some(matches)) => profile::exec(context, matches),
+        ("pull", Some(matches)) => pull::exec(context, matches),
+        ("push", Some(matches)) => push::exec(context, matches),
+        ("remote", Some(matches)) => remote::exec(context, matches),
+        ("status", Some(matches)) => status::exec(context, matches),
+        ("sync", Some(matches)) => sync::exec(context, matches),
+        ("undo", Some(matches)) => undo::exec(context, matches),
+        (cmd, _) => Err(Error::new(Kind::Usage, format!("'{}' is not a valid COMMAND.", cmd))),
+    }
+}
@@ -0,0 +1,29 @@
+use clap::App;
+use clap::AppSettings;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
+use core::context::Context;
+use utils::error::Error;
+use utils::error::Kind;
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("profile")
+        .about("Choose the profile of sections used on this machine")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("use")
+            .about("Copy only the sections of the profile on this machine, the choice is not synced")
+            .arg(Arg::with_name("NAME")
+                .required(true)
+                .help("Profile listed in the config")))
+        .subcommand(SubCommand::with_name("clear")
+            .about("Copy all the sections again on this machine"))
+}
+
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    match matches.subcommand() {
+        ("use", Some(matches)) => context.repository().profile_use(matches.value_of("NAME")),
+        ("clear", Some(_)) => context.repository().profile_use(None),
+        (cmd, _) => Err(Error::new(Kind::Usage, format!("'{}' is not a valid profile COMMAND.", cmd))),
+    }
+}
@@ -1,6 +1,19 @@
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
 use core::context::Context;
 use utils::error::Error;
 
-pub fn exec(context: &mut Context, _: &mut Vec<String>) -> Result<(), Error> {
-    context.repository().pull()
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("pull")
+        .about("Backup local files, update the working branch and copy its files to local disk")
+        .arg(Arg::with_name("remote")
+            .long("remote")
+            .value_name("NAME")
+            .help("Remote to fetch and push [default: remote tracked by the branch or in config]"))
+}
+
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    context.repository().pull(matches.value_of("remote"))
 }
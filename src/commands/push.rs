@@ -1,6 +1,27 @@
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
 use core::context::Context;
 use utils::error::Error;
 
-pub fn exec(context: &mut Context, _: &mut Vec<String>) -> Result<(), Error> {
-    context.repository().push()
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("push")
+        .about("Commit local files to the working branch, pull and push it")
+        .arg(Arg::with_name("remote")
+            .long("remote")
+            .value_name("NAME")
+            .help("Remote to fetch and push [default: remote tracked by the branch or in config]"))
+        .arg(Arg::with_name("message")
+            .long("message")
+            .short("m")
+            .value_name("MESSAGE")
+            .help("Message of the commit [default: commit_message in config or the list of changed paths]"))
+        .arg(Arg::with_name("offline")
+            .long("offline")
+            .help("Only commit local files and queue the push for the next online push or sync"))
+}
+
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    context.repository().push(matches.value_of("remote"), matches.is_present("offline"), matches.value_of("message"))
 }
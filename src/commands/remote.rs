@@ -0,0 +1,36 @@
+use clap::App;
+use clap::AppSettings;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
+use core::context::Context;
+use utils::error::Error;
+use utils::error::Kind;
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("remote")
+        .about("Show and change the remote branch tracked by each working branch")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("show")
+            .about("Show the remote branch tracked by each working branch"))
+        .subcommand(SubCommand::with_name("set")
+            .about("Set or change the remote branch tracked by a working branch")
+            .arg(Arg::with_name("UPSTREAM")
+                .required(true)
+                .help("REMOTE/BRANCH to track, or REMOTE to track the branch with the same name"))
+            .arg(Arg::with_name("branch")
+                .long("branch")
+                .value_name("NAME")
+                .help("Working branch to change [default: current branch]")))
+}
+
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    match matches.subcommand() {
+        ("show", Some(_)) => context.repository().remote_show(),
+        ("set", Some(matches)) => {
+            let upstream = matches.value_of("UPSTREAM").unwrap_or_default();
+            context.repository().remote_set(matches.value_of("branch"), upstream)
+        }
+        (cmd, _) => Err(Error::new(Kind::Usage, format!("'{}' is not a valid remote COMMAND.", cmd))),
+    }
+}
@@ -0,0 +1,14 @@
+use clap::App;
+use clap::ArgMatches;
+use clap::SubCommand;
+use core::context::Context;
+use utils::error::Error;
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("status")
+        .about("Show the working branch and the local files not in sync with the repository")
+}
+
+pub fn exec(context: &mut Context, _: &ArgMatches) -> Result<(), Error> {
+    context.repository().status()
+}
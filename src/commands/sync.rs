@@ -0,0 +1,19 @@
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
+use core::context::Context;
+use utils::error::Error;
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("sync")
+        .about("Push the commits queued while offline, merging the remote changes first if needed")
+        .arg(Arg::with_name("remote")
+            .long("remote")
+            .value_name("NAME")
+            .help("Remote to fetch and push [default: remote tracked by the branch or in config]"))
+}
+
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    context.repository().sync(matches.value_of("remote"))
+}
@@ -0,0 +1,18 @@
+use clap::App;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
+use core::context::Context;
+use utils::error::Error;
+
+pub fn command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("undo")
+        .about("Revert the last pull or push")
+        .arg(Arg::with_name("force-revert")
+            .long("force-revert")
+            .help("Push a revert commit instead of resetting the working branch, only for a push that reached the remote"))
+}
+
+pub fn exec(context: &mut Context, matches: &ArgMatches) -> Result<(), Error> {
+    context.repository().undo(matches.is_present("force-revert"))
+}
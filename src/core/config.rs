@@ -1,46 +1,570 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Component;
+use std::path::Path;
 use std::path::PathBuf;
 use std::fs::File;
 use std::io::Read;
 use std::str;
+use serde_json;
 use serde_yaml;
+use toml;
+use core::report::Diagnostic;
+use regex;
+use regex::Regex;
+use serde::de::DeserializeOwned;
 use utils::error::Error;
+use utils::error::Kind;
+use utils::expand;
+use utils::host;
+use utils::sign;
 
-#[derive(Deserialize)]
+/// File format of the config, detected by the extension
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl Format {
+    /// All the formats, if more config files exist the first one is used
+    pub const PRECEDENCE: [Format; 3] = [Format::Yaml, Format::Toml, Format::Json];
+
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            Format::Yaml => "yaml",
+            Format::Toml => "toml",
+            Format::Json => "json",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        Format::PRECEDENCE.iter().cloned().find(|format| format.extension() == extension)
+    }
+
+    pub fn from_path(path: &Path) -> Format {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Format::from_extension)
+            .unwrap_or(Format::Yaml)
+    }
+}
+
+/// Return the config file in dir: config.yaml, config.toml or config.json in this order of
+/// precedence, config.yaml if none of them exists
+pub fn find(dir: &Path) -> PathBuf {
+    Format::PRECEDENCE.iter()
+        .map(|format| dir.join(format!("config.{}", format.extension())))
+        .find(|file| file.is_file())
+        .unwrap_or_else(|| dir.join("config.yaml"))
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    /// Remote to fetch and push when the working branch does not track any remote
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remote: Option<String>,
+    /// Other remotes the working branch is pushed to as mirrors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    push_remotes: Vec<String>,
+    /// Template of the push commit messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    commit_message: Option<String>,
+    /// Author of the commits, by default the git user or scog@hostname
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<Author>,
+    /// Key used to sign the commits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signing: Option<Signing>,
+    /// Named subsets of the sections, one of them can be activated on each machine
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    profiles: BTreeMap<String, Profile>,
+    /// Other files with sections, relative to the dir of this file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    include: Vec<String>,
     sections: Vec<Section>,
+    /// Sections of the included files, in the order they are included
+    #[serde(skip)]
+    included: Vec<Section>,
 }
 
+/// A file listed by include, it can only add sections and include other files
 #[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Included {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    sections: Vec<Section>,
+}
+
+/// An included file with its content and sections, used to report problems in the right file
+struct Part {
+    file: PathBuf,
+    content: String,
+    sections: Vec<Section>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Author {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Signing {
+    format: sign::Format,
+    /// GPG key id or path of the SSH private key
+    key: String,
+}
+
+/// Sections listed by path or selected by tag
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sections: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Section {
+    /// Path on local disk, relative to the home dir or starting with ~ or a variable
     path: String,
+    /// Path in the repository when it differs from the path on local disk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    repo_path: Option<String>,
+    /// How the local file is synced
+    #[serde(default, skip_serializing_if = "Mode::is_copy")]
+    mode: Mode,
+    /// Name in the markers of mode block, by default the file name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    block: Option<String>,
+    /// How the files are merged when both machines changed them
+    #[serde(default, skip_serializing_if = "Merge::is_text")]
+    merge: Merge,
+    /// Keys of the files overridden on this machine by the files in .local of the repository dir
+    #[serde(default, skip_serializing_if = "is_false")]
+    local_overrides: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// Conditions this machine must meet to copy the section
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    when: Option<When>,
+}
+
+/// How a section is synced
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// The whole file or dir is copied
+    #[default]
+    Copy,
+    /// Only the lines between the scog markers of the local file are synced
+    Block,
+}
+
+impl Mode {
+    fn is_copy(&self) -> bool {
+        *self == Mode::Copy
+    }
+}
+
+/// How the files of a section are merged
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Merge {
+    /// Line by line, the same lines changed on both sides conflict
+    #[default]
+    Text,
+    /// Key by key for YAML, JSON, TOML and INI files, the same key changed on both sides conflicts
+    Structured,
+}
+
+impl Merge {
+    fn is_text(&self) -> bool {
+        *self == Merge::Text
+    }
+}
+
+/// Conditions on the machine, all of them must be true
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct When {
+    /// Glob matched with the hostname, like laptop-*
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hostname: Option<String>,
+    /// Operating system, like linux or macos
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    os: Option<String>,
+    /// ID or ID_LIKE of /etc/os-release, like arch or debian
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    distro: Option<String>,
+    /// Executable that must be on PATH
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    executable: Option<String>,
+    /// Environment variables and their expected values
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
 }
 
 impl Config {
     pub fn new(config: &PathBuf) -> Result<Config, Error> {
+        let content = read(config)?;
 
-        // Test config file
-        if !config.is_file() {
-            return Err(format!("the config file '{}' does not exists.", config.to_string_lossy()))?;
+        // Parse config file to Config struct
+        let format = Format::from_path(config);
+        let mut parsed: Config = parse(format, content.as_str()).map_err(|(message, location)| {
+            let message = match location {
+                Some((line, column)) => format!("invalid {}: {} at line {} column {}", format.extension(), message, line, column),
+                None => format!("invalid {}: {}", format.extension(), message),
+            };
+            Error::new(Kind::Config, message).with_path(config)
+        })?;
+
+        // Merge the sections of the included files, the first problem is the error
+        let mut diagnostics: Vec<Diagnostic> = vec![];
+        let parts = includes(config, content.as_str(), &parsed.include, &mut diagnostics);
+        if let Some(diagnostic) = diagnostics.into_iter().next() {
+            let message = match (diagnostic.line, diagnostic.column) {
+                (Some(line), Some(column)) => format!("{} at line {} column {}", diagnostic.message, line, column),
+                _ => diagnostic.message,
+            };
+            return Err(Error::new(Kind::Config, message).with_path(&diagnostic.file));
+        }
+        for mut part in parts {
+            parsed.included.append(&mut part.sections);
+        }
+        Ok(parsed)
+    }
+
+    /// Parse and check the config file, return a diagnostic for each problem found
+    pub fn check(config: &PathBuf) -> Result<Vec<Diagnostic>, Error> {
+        let content = read(config)?;
+        let mut diagnostics = match parse::<Config>(Format::from_path(config), content.as_str()) {
+            Ok(mut parsed) => {
+                let mut diagnostics: Vec<Diagnostic> = vec![];
+                let parts = includes(config, content.as_str(), &parsed.include, &mut diagnostics);
+                let mut files = vec![(config.as_path(), content.as_str(), &parsed.sections)];
+                files.extend(parts.iter().map(|part| (part.file.as_path(), part.content.as_str(), &part.sections)));
+                diagnostics.append(&mut check_sections(&files));
+
+                // Profiles can use the sections of the included files
+                parsed.included = parts.iter().flat_map(|part| part.sections.iter().cloned()).collect();
+                diagnostics.append(&mut parsed.check_profiles(config, content.as_str()));
+                diagnostics
+            }
+            Err((message, location)) => vec![Diagnostic {
+                file: config.to_path_buf(),
+                line: location.map(|(line, _)| line),
+                column: location.map(|(_, column)| column),
+                message,
+            }],
+        };
+
+        // Config files in other formats are ignored
+        if let Some(dir) = config.parent() {
+            for format in Format::PRECEDENCE.iter() {
+                let other = dir.join(format!("config.{}", format.extension()));
+                if other != *config && other.is_file() {
+                    let message = format!("the file is ignored because {} has precedence, remove one of them", config.to_string_lossy());
+                    diagnostics.push(Diagnostic { file: other, line: None, column: None, message });
+                }
+            }
         }
+        Ok(diagnostics)
+    }
 
-        // Open and read config file
-        let mut config = File::open(config)?;
-        let mut buffer = Vec::new();
-        config.read_to_end(&mut buffer)?;
+    /// Check that the profiles list existing sections and tags used by some section
+    fn check_profiles(&self, config: &Path, content: &str) -> Vec<Diagnostic> {
+        let sections = self.sections();
+        let mut diagnostics: Vec<Diagnostic> = vec![];
+        for (name, profile) in &self.profiles {
+            let mut diagnostic = |value: &str, message: String| {
+                let location = item_location(content, value);
+                diagnostics.push(Diagnostic {
+                    file: config.to_path_buf(),
+                    line: location.map(|(line, _)| line),
+                    column: location.map(|(_, column)| column),
+                    message,
+                })
+            };
+            for path in &profile.sections {
+                if !sections.iter().any(|section| section.path == *path || normalize(&section.path()) == normalize(Path::new(path))) {
+                    diagnostic(path, format!("the profile '{}' lists '{}' that is not a section", name, path));
+                }
+            }
+            for tag in &profile.tags {
+                if !sections.iter().any(|section| section.tags.contains(tag)) {
+                    diagnostic(tag, format!("the profile '{}' uses the tag '{}' that no section has", name, tag));
+                }
+            }
+        }
+        diagnostics
+    }
 
-        // Parse toml config file to Config struct
-        let content = str::from_utf8(&buffer)?;
-        Ok(serde_yaml::from_str(content)?)
+    pub fn from_sections(sections: Vec<Section>) -> Config {
+        Config {
+            remote: None,
+            push_remotes: vec![],
+            commit_message: None,
+            author: None,
+            signing: None,
+            profiles: BTreeMap::new(),
+            include: vec![],
+            sections,
+            included: vec![],
+        }
     }
 
-    pub fn sections(&self) -> &Vec<Section> {
-        &self.sections
+    /// Write the config to the passed file in the format of its extension
+    pub fn save(&self, config: &PathBuf) -> Result<(), Error> {
+        let content = match Format::from_path(config) {
+            Format::Yaml => serde_yaml::to_string(self)?,
+            // Going through a toml value emits the plain keys before the tables
+            Format::Toml => toml::to_string(&toml::Value::try_from(self)?)?,
+            Format::Json => format!("{}\n", serde_json::to_string_pretty(self)?),
+        };
+        fs::write(config, content).map_err(|e| Error::from(e).with_path(config))?;
+        Ok(())
+    }
+
+    pub fn remote(&self) -> Option<&str> {
+        self.remote.as_deref()
+    }
+
+    pub fn push_remotes(&self) -> &Vec<String> {
+        &self.push_remotes
+    }
+
+    pub fn commit_message(&self) -> Option<&str> {
+        self.commit_message.as_deref()
+    }
+
+    pub fn author(&self) -> Option<&Author> {
+        self.author.as_ref()
+    }
+
+    pub fn signing(&self) -> Option<&Signing> {
+        self.signing.as_ref()
+    }
+
+    /// The sections of this file followed by the ones of the included files
+    pub fn sections(&self) -> Vec<Section> {
+        self.sections.iter().chain(self.included.iter()).cloned().collect()
+    }
+
+    /// Map a path on local disk, relative to the home dir, to its path in the repository
+    pub fn to_repository(&self, local: &Path) -> PathBuf {
+        for section in self.sections() {
+            if let Ok(rest) = local.strip_prefix(section.path()) {
+                return join(section.repo_path(), rest);
+            }
+        }
+        local.to_path_buf()
+    }
+
+    /// Map a path in the repository to its path on local disk, relative to the home dir
+    pub fn to_local(&self, repository: &Path) -> PathBuf {
+        for section in self.sections() {
+            if let Ok(rest) = repository.strip_prefix(section.repo_path()) {
+                return join(section.path(), rest);
+            }
+        }
+        repository.to_path_buf()
+    }
+
+    /// Paths in the repository of the sections merged key by key
+    pub fn structured_paths(&self) -> Vec<PathBuf> {
+        self.sections().iter()
+            .filter(|section| section.merge == Merge::Structured)
+            .map(|section| section.repo_path())
+            .collect()
+    }
+
+    /// Name of the block synced in the local file, None if the file is copied whole
+    pub fn block_name(&self, local: &Path) -> Option<String> {
+        self.sections().iter()
+            .find(|section| section.path() == local)
+            .and_then(|section| section.block_name())
+    }
+
+    /// Whether the local file, or the dir of the section containing it, keeps machine-local overrides
+    pub fn local_overrides(&self, local: &Path) -> bool {
+        self.sections().iter()
+            .any(|section| local.starts_with(section.path()) && section.local_overrides())
+    }
+
+    pub fn profiles(&self) -> &BTreeMap<String, Profile> {
+        &self.profiles
+    }
+
+    /// The sections in the profile, all of them if the profile is None
+    pub fn profile_sections(&self, profile: Option<&str>) -> Result<Vec<Section>, Error> {
+        let name = match profile {
+            Some(name) => name,
+            None => return Ok(self.sections()),
+        };
+        match self.profiles.get(name) {
+            Some(profile) => Ok(self.sections().into_iter().filter(|section| profile.contains(section)).collect()),
+            None => Err(Error::new(Kind::Config, format!("the profile '{}' is not in the config, change it with 'scog profile use'", name))),
+        }
+    }
+}
+
+impl Profile {
+    /// The section is listed by path or has one of the tags
+    pub fn contains(&self, section: &Section) -> bool {
+        let path = normalize(&section.path());
+        self.sections.iter().any(|other| *other == section.path || normalize(Path::new(other)) == path)
+            || section.tags.iter().any(|tag| self.tags.contains(tag))
+    }
+}
+
+impl When {
+    /// The reason the machine does not meet the conditions, None if it meets all of them
+    pub fn unmet(&self) -> Option<String> {
+        if let Some(ref pattern) = self.hostname {
+            let hostname = host::hostname();
+            if !host::glob_matches(pattern, hostname.as_str()) {
+                return Some(format!("the hostname '{}' does not match '{}'", hostname, pattern));
+            }
+        }
+        if let Some(ref os) = self.os {
+            if host::os() != os {
+                return Some(format!("the os is '{}', not '{}'", host::os(), os));
+            }
+        }
+        if let Some(ref distro) = self.distro {
+            let distros = host::distros();
+            if !distros.contains(distro) {
+                return match distros.first() {
+                    Some(found) => Some(format!("the distro is '{}', not '{}'", found, distro)),
+                    None => Some(format!("the distro is unknown, not '{}'", distro)),
+                };
+            }
+        }
+        if let Some(ref executable) = self.executable {
+            if !host::has_executable(executable) {
+                return Some(format!("'{}' is not on PATH", executable));
+            }
+        }
+        for (name, expected) in &self.env {
+            match env::var(name) {
+                Ok(ref value) if value == expected => {}
+                Ok(value) => return Some(format!("${} is '{}', not '{}'", name, value, expected)),
+                Err(_) => return Some(format!("${} is not set, not '{}'", name, expected)),
+            }
+        }
+        None
     }
 }
 
 impl Section {
+    pub fn new(path: String) -> Section {
+        Section {
+            path,
+            repo_path: None,
+            mode: Mode::Copy,
+            block: None,
+            merge: Merge::Text,
+            local_overrides: false,
+            tags: vec![],
+            when: None,
+        }
+    }
+
+    /// The path on local disk with ~ and the variables expanded, relative to the home dir when inside it
     pub fn path(&self) -> PathBuf {
-        PathBuf::from(&self.path)
+        let home = env::home_dir().unwrap_or_default();
+        let path = PathBuf::from(expand::expand(self.path.as_str(), &home).0);
+        match path.strip_prefix(&home) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => path,
+        }
+    }
+
+    /// Why the path on local disk is not valid: its variables must be set and once expanded
+    /// it must be relative to the home dir and inside it
+    pub fn path_problem(&self) -> Option<String> {
+        if let Some(name) = self.unset_variables().first() {
+            return Some(format!("the variable '{}' in '{}' is not set", name, self.path));
+        }
+        let path = self.path();
+        if path.has_root() {
+            return match Path::new(&self.path).has_root() {
+                true => Some(format!("the path '{}' must be relative to the home dir", self.path)),
+                false => Some(format!("the path '{}' expands to '{}' outside the home dir", self.path, path.to_string_lossy())),
+            };
+        }
+        match normalize(path.as_path()) {
+            Some(_) => None,
+            None => Some(format!("the path '{}' is outside the home dir", self.path)),
+        }
+    }
+
+    /// The path in the repository, the path on local disk without repo_path
+    pub fn repo_path(&self) -> PathBuf {
+        match self.repo_path {
+            Some(ref repo_path) => PathBuf::from(repo_path),
+            None => self.path(),
+        }
+    }
+
+    /// Name of the block synced in the local file, None if the whole file is copied
+    pub fn block_name(&self) -> Option<String> {
+        match self.mode {
+            Mode::Copy => None,
+            Mode::Block => Some(match self.block {
+                Some(ref name) => name.clone(),
+                None => self.path().file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+            }),
+        }
+    }
+
+    /// The files are written to local disk with the machine-local overrides on top
+    pub fn local_overrides(&self) -> bool {
+        self.local_overrides
+    }
+
+    /// Variables of the path that are not set
+    fn unset_variables(&self) -> Vec<String> {
+        expand::expand(self.path.as_str(), &env::home_dir().unwrap_or_default()).1
+    }
+
+    /// The reason the section is skipped on this machine, None if it is copied
+    pub fn unmet(&self) -> Option<String> {
+        self.when.as_ref().and_then(|when| when.unmet())
+    }
+
+}
+
+impl Author {
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+}
+
+impl Signing {
+    pub fn format(&self) -> sign::Format {
+        self.format
+    }
+
+    pub fn key(&self) -> &str {
+        self.key.as_str()
     }
 }
 
@@ -50,12 +574,431 @@ impl Clone for Config {
         for section in &self.sections {
             sections.push(section.clone());
         }
-        Config { sections }
+        Config {
+            remote: self.remote.clone(),
+            push_remotes: self.push_remotes.clone(),
+            commit_message: self.commit_message.clone(),
+            author: self.author.clone(),
+            signing: self.signing.clone(),
+            profiles: self.profiles.clone(),
+            include: self.include.clone(),
+            sections,
+            included: self.included.clone(),
+        }
     }
 }
 
 impl Clone for Section {
     fn clone(&self) -> Self {
-        Section { path: self.path.clone() }
+        Section {
+            path: self.path.clone(),
+            repo_path: self.repo_path.clone(),
+            mode: self.mode,
+            block: self.block.clone(),
+            merge: self.merge,
+            local_overrides: self.local_overrides,
+            tags: self.tags.clone(),
+            when: self.when.clone(),
+        }
+    }
+}
+
+/// Read the config file
+fn read(config: &PathBuf) -> Result<String, Error> {
+
+    // Test config file
+    if !config.is_file() {
+        return Err(Error::new(Kind::Config, "the config file does not exists".to_string()).with_path(config));
     }
-}
\ No newline at end of file
+
+    // Open and read config file
+    let mut file = File::open(config).map_err(|e| Error::from(e).with_path(config))?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).map_err(|e| Error::from(e).with_path(config))?;
+
+    let content = str::from_utf8(&buffer).map_err(|e| Error::from(e).with_path(config))?;
+    Ok(content.to_string())
+}
+
+/// Check the section paths of each file: they must be relative to the home dir, inside it, and listed once
+/// without overlapping other sections
+fn check_sections(files: &[(&Path, &str, &Vec<Section>)]) -> Vec<Diagnostic> {
+    let root = files.first().and_then(|(file, _, _)| file.parent()).unwrap_or_else(|| Path::new(""));
+
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+    let mut checked: Vec<(PathBuf, Option<PathBuf>, &Path, _)> = vec![];
+    for (file, content, sections) in files {
+        // Sections are matched with the path keys in the file by position
+        let mut locations = path_locations(content);
+        if locations.len() != sections.len() {
+            locations = vec![None; sections.len()];
+        }
+
+        for (section, location) in sections.iter().zip(locations) {
+            let mut diagnostic = |message: String| diagnostics.push(Diagnostic {
+                file: file.to_path_buf(),
+                line: location.map(|(line, _)| line),
+                column: location.map(|(_, column)| column),
+                message,
+            });
+
+            if section.path.is_empty() {
+                diagnostic("the path is empty".to_string());
+                continue;
+            }
+            if let (Mode::Copy, Some(ref name)) = (section.mode, &section.block) {
+                diagnostic(format!("the block '{}' is only used with mode block", name));
+                continue;
+            }
+
+            // The path on local disk must have its variables set and stay inside the home dir even with a repo_path
+            if let Some(problem) = section.path_problem() {
+                diagnostic(problem);
+                continue;
+            }
+
+            // Without repo_path the path on local disk is also the path in the repository
+            let local = normalize(section.path().as_path()).unwrap_or_default();
+            let repository = match section.repo_path {
+                Some(ref repo_path) => {
+                    match normalize(Path::new(repo_path)) {
+                        Some(path) if !path.as_os_str().is_empty() => Some(path),
+                        _ => {
+                            diagnostic(format!("the repo_path '{}' must be relative and inside the repository", repo_path));
+                            continue;
+                        }
+                    }
+                }
+                None => None,
+            };
+
+            // Two sections can not write the same files on local disk nor in the repository
+            for (other_local, other_repository, other_file, other_location) in &checked {
+                let mut listed = match *other_location {
+                    Some((line, _)) => format!("listed at line {}", line),
+                    None => "listed before".to_string(),
+                };
+                if other_file != file {
+                    let name = other_file.strip_prefix(root).unwrap_or(other_file);
+                    listed = format!("{} in {}", listed, name.to_string_lossy());
+                }
+                let what = format!("the path '{}'", section.path);
+                if let Some(message) = overlap(what, &local, other_local, listed.as_str()) {
+                    diagnostic(message);
+                    continue;
+                }
+                if repository.is_some() || other_repository.is_some() {
+                    let what = format!("the repo_path '{}'", section.repo_path.as_ref().unwrap_or(&section.path));
+                    let path = repository.as_ref().unwrap_or(&local);
+                    let other = other_repository.as_ref().unwrap_or(other_local);
+                    if let Some(message) = overlap(what, path, other, listed.as_str()) {
+                        diagnostic(message);
+                    }
+                }
+            }
+            checked.push((local, repository, file, location));
+        }
+    }
+    diagnostics
+}
+
+/// Describe how the path overlaps the other path, None if they are unrelated
+fn overlap(what: String, path: &Path, other: &Path, listed: &str) -> Option<String> {
+    if path == other {
+        Some(format!("{} is already {}", what, listed))
+    } else if path.starts_with(other) {
+        Some(format!("{} is inside '{}' {}", what, other.to_string_lossy(), listed))
+    } else if other.starts_with(path) {
+        Some(format!("{} contains '{}' {}", what, other.to_string_lossy(), listed))
+    } else {
+        None
+    }
+}
+
+/// Load the files included by the config, depth first and each one once. Include paths are relative to the
+/// dir of the file listing them and must stay inside the dir of the config, problems are added to diagnostics
+fn includes(config: &Path, content: &str, include: &[String], diagnostics: &mut Vec<Diagnostic>) -> Vec<Part> {
+    let root = config.parent().unwrap_or_else(|| Path::new(""));
+    let mut parts: Vec<Part> = vec![];
+    let mut stack: Vec<PathBuf> = vec![config.to_path_buf()];
+    load_includes(root, content, include, &mut stack, &mut parts, diagnostics);
+    parts
+}
+
+/// Load the files included by the last file of the stack, the stack is the chain of files including it
+fn load_includes(root: &Path, content: &str, include: &[String], stack: &mut Vec<PathBuf>, parts: &mut Vec<Part>, diagnostics: &mut Vec<Diagnostic>) {
+    let file = stack.last().cloned().unwrap_or_default();
+    for name in include {
+        let location = item_location(content, name);
+        let mut diagnostic = |message: String| diagnostics.push(Diagnostic {
+            file: file.clone(),
+            line: location.map(|(line, _)| line),
+            column: location.map(|(_, column)| column),
+            message,
+        });
+
+        if Path::new(name).has_root() {
+            diagnostic(format!("the include '{}' must be relative to the config dir", name));
+            continue;
+        }
+        let dir = file.parent().and_then(|dir| dir.strip_prefix(root).ok()).unwrap_or_else(|| Path::new(""));
+        let included = match normalize(dir.join(name).as_path()) {
+            Some(path) => root.join(path),
+            None => {
+                diagnostic(format!("the include '{}' is outside the config dir", name));
+                continue;
+            }
+        };
+        if let Some(index) = stack.iter().position(|other| *other == included) {
+            let chain: Vec<String> = stack[index..].iter().chain(Some(&included))
+                .map(|other| other.strip_prefix(root).unwrap_or(other).to_string_lossy().to_string())
+                .collect();
+            diagnostic(format!("the include '{}' is a cycle: {}", name, chain.join(" -> ")));
+            continue;
+        }
+        if parts.iter().any(|part| part.file == included) {
+            continue;
+        }
+        if !included.is_file() {
+            diagnostic(format!("the include '{}' does not exist", name));
+            continue;
+        }
+
+        let included_content = match read(&included) {
+            Ok(content) => content,
+            Err(error) => {
+                diagnostic(error.to_string());
+                continue;
+            }
+        };
+        match parse::<Included>(Format::from_path(&included), included_content.as_str()) {
+            Ok(parsed) => {
+                parts.push(Part { file: included.clone(), content: included_content.clone(), sections: parsed.sections });
+                stack.push(included);
+                load_includes(root, included_content.as_str(), &parsed.include, stack, parts, diagnostics);
+                stack.pop();
+            }
+            Err((message, location)) => diagnostics.push(Diagnostic {
+                file: included,
+                line: location.map(|(line, _)| line),
+                column: location.map(|(_, column)| column),
+                message,
+            }),
+        }
+    }
+}
+
+/// Parse the content in the format, on error return the message and its line and column
+fn parse<T: DeserializeOwned>(format: Format, content: &str) -> Result<T, (String, Option<(usize, usize)>)> {
+    let location = Regex::new(r" at line \d+ column \d+$").unwrap();
+    let message = |error: String| location.replace(error.as_str(), "").to_string();
+    match format {
+        Format::Yaml => serde_yaml::from_str(content).map_err(|error| {
+            let location = error.location().map(|location| (location.line(), location.column()));
+            (message(error.to_string()), location)
+        }),
+        Format::Toml => toml::from_str(content).map_err(|error| {
+            let location = error.line_col().map(|(line, column)| (line + 1, column + 1));
+            (message(error.to_string()), location)
+        }),
+        Format::Json => serde_json::from_str(content).map_err(|error| {
+            let location = Some((error.line(), error.column()));
+            (message(error.to_string()), location)
+        }),
+    }
+}
+
+/// Line and column of the value of each path key, in order
+fn path_locations(content: &str) -> Vec<Option<(usize, usize)>> {
+    let key = Regex::new(r#"^(\s*-?\s*"?path"?\s*[:=]\s*)\S"#).unwrap();
+    content.lines().enumerate()
+        .filter_map(|(index, line)| key.captures(line).map(|captures| Some((index + 1, captures[1].len() + 1))))
+        .collect()
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Join the rest of a path without adding a trailing separator when it is empty
+fn join(path: PathBuf, rest: &Path) -> PathBuf {
+    match rest.as_os_str().is_empty() {
+        true => path,
+        false => path.join(rest),
+    }
+}
+
+/// Line and column of the first list entry equal to the value: a yaml `- value` item
+/// or a quoted item of a toml or json array, a longer entry containing the value is skipped
+fn item_location(content: &str, value: &str) -> Option<(usize, usize)> {
+    let pattern = format!(r#"^(\s*-\s*["']?|.*[\[,]\s*["']|\s*["']){}["']?\s*(,|\]|$)"#, regex::escape(value));
+    let item = Regex::new(pattern.as_str()).unwrap();
+    content.lines().enumerate()
+        .find_map(|(index, line)| item.captures(line).map(|captures| (index + 1, captures[1].len() + 1)))
+}
+
+/// Resolve the . and .. components, None if the path goes above its root
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_sections;
+    use super::item_location;
+    use super::parse;
+    use super::Config;
+    use super::Format;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    fn diagnostics(content: &str) -> Vec<(Option<usize>, String)> {
+        let config: Config = ::serde_yaml::from_str(content).unwrap();
+        check_sections(&[(Path::new("config.yaml"), content, &config.sections)]).into_iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_item_location() {
+        let yaml = "include:\n- groups/work.yaml\n- 'work.yaml'\n";
+        assert_eq!(item_location(yaml, "work.yaml"), Some((3, 4)));
+        assert_eq!(item_location(yaml, "groups/work.yaml"), Some((2, 3)));
+        let toml = "include = [\"groups/work.yaml\", \"work.yaml\"]\n";
+        assert_eq!(item_location(toml, "work.yaml"), Some((1, 33)));
+        let json = "{\n  \"include\": [\n    \"groups/work.yaml\",\n    \"work.yaml\"\n  ]\n}\n";
+        assert_eq!(item_location(json, "work.yaml"), Some((4, 6)));
+        assert_eq!(item_location(yaml, "work"), None);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let content = "sections:\n- path: .bashrc\n- path: /home/davide/.vimrc\n- path: ../.zshrc\n- path: .config\n- path: .config/nvim\n- path: ./.bashrc\n";
+        assert_eq!(diagnostics(content), vec![
+            (Some(3), "the path '/home/davide/.vimrc' must be relative to the home dir".to_string()),
+            (Some(4), "the path '../.zshrc' is outside the home dir".to_string()),
+            (Some(6), "the path '.config/nvim' is inside '.config' listed at line 5".to_string()),
+            (Some(7), "the path './.bashrc' is already listed at line 2".to_string()),
+        ]);
+        assert!(diagnostics("sections:\n- path: .bashrc\n- path: a/../.vimrc\n").is_empty());
+    }
+
+    #[test]
+    fn test_unknown_key() {
+        let error = ::serde_yaml::from_str::<Config>("sections:\n- path: .bashrc\n  strategy: copy\n").err().unwrap();
+        assert!(error.to_string().contains("unknown field `strategy`"));
+        assert_eq!(error.location().unwrap().line(), 3);
+    }
+
+    #[test]
+    fn test_formats() {
+        let yaml = "remote: origin\nsections:\n- path: .bashrc\n";
+        let toml = "remote = \"origin\"\n\n[[sections]]\npath = \".bashrc\"\n";
+        let json = "{\"remote\": \"origin\", \"sections\": [{\"path\": \".bashrc\"}]}";
+        for (format, content) in [(Format::Yaml, yaml), (Format::Toml, toml), (Format::Json, json)].iter() {
+            let config: Config = parse(*format, content).ok().unwrap();
+            assert_eq!(config.remote(), Some("origin"));
+            assert_eq!(config.sections()[0].path(), PathBuf::from(".bashrc"));
+        }
+        assert_eq!(::toml::to_string(&::toml::Value::try_from(parse::<Config>(Format::Yaml, yaml).ok().unwrap()).unwrap()).unwrap(), toml);
+        assert_eq!(parse::<Config>(Format::Toml, "sections = 1").err().unwrap().1, Some((1, 12)));
+    }
+
+    #[test]
+    fn test_profiles() {
+        let content = "profiles:\n  server:\n    sections:\n    - .bashrc\n    - .vimrc\n    tags:\n    - cli\n    - shell\nsections:\n- path: .bashrc\n- path: .tmux.conf\n  tags:\n  - cli\n- path: .config/sway\n  tags:\n  - gui\n";
+        let config: Config = ::serde_yaml::from_str(content).unwrap();
+        let paths: Vec<PathBuf> = config.profile_sections(Some("server")).unwrap().iter().map(|section| section.path()).collect();
+        assert_eq!(paths, vec![PathBuf::from(".bashrc"), PathBuf::from(".tmux.conf")]);
+        assert_eq!(config.profile_sections(None).unwrap().len(), 3);
+        assert!(config.profile_sections(Some("laptop")).is_err());
+
+        let diagnostics: Vec<(Option<usize>, String)> = config.check_profiles(Path::new("config.yaml"), content).into_iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.message))
+            .collect();
+        assert_eq!(diagnostics, vec![
+            (Some(5), "the profile 'server' lists '.vimrc' that is not a section".to_string()),
+            (Some(8), "the profile 'server' uses the tag 'shell' that no section has".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_repo_path() {
+        let content = "sections:\n- path: .bashrc\n- path: ~/.local/share/fonts\n  repo_path: fonts\n- path: .vimrc\n  repo_path: ../vimrc\n";
+        let config: Config = ::serde_yaml::from_str(content).unwrap();
+        assert_eq!(config.to_repository(Path::new(".local/share/fonts/mono.ttf")), PathBuf::from("fonts/mono.ttf"));
+        assert_eq!(config.to_repository(Path::new(".local/share/fonts")), PathBuf::from("fonts"));
+        assert_eq!(config.to_local(Path::new("fonts/mono.ttf")), PathBuf::from(".local/share/fonts/mono.ttf"));
+        assert_eq!(config.to_repository(Path::new(".bashrc")), PathBuf::from(".bashrc"));
+        assert_eq!(diagnostics(content), vec![
+            (Some(5), "the repo_path '../vimrc' must be relative and inside the repository".to_string()),
+        ]);
+        let content = "sections:\n- path: /etc/cron.d/backup\n  repo_path: cron\n- path: ../../etc/hosts\n  repo_path: hosts\n";
+        assert_eq!(diagnostics(content), vec![
+            (Some(2), "the path '/etc/cron.d/backup' must be relative to the home dir".to_string()),
+            (Some(4), "the path '../../etc/hosts' is outside the home dir".to_string()),
+        ]);
+
+        // Local paths and repo paths are both checked for duplicates and overlaps
+        let content = "sections:\n- path: .bashrc\n- path: ~/.bashrc\n  repo_path: other\n- path: .config\n- path: .config/nvim\n  repo_path: nvim\n- path: .zshrc\n  repo_path: .config\n";
+        assert_eq!(diagnostics(content), vec![
+            (Some(3), "the path '~/.bashrc' is already listed at line 2".to_string()),
+            (Some(6), "the path '.config/nvim' is inside '.config' listed at line 5".to_string()),
+            (Some(8), "the repo_path '.config' is already listed at line 5".to_string()),
+        ]);
+
+        assert_eq!(diagnostics("sections:\n- path: $SCOG_TEST_UNSET/.vimrc\n  repo_path: vimrc\n"), vec![
+            (Some(2), "the variable 'SCOG_TEST_UNSET' in '$SCOG_TEST_UNSET/.vimrc' is not set".to_string()),
+        ]);
+
+        env::set_var("SCOG_TEST_OUTSIDE", "/etc");
+        assert_eq!(diagnostics("sections:\n- path: $SCOG_TEST_OUTSIDE/x\n  repo_path: x\n"), vec![
+            (Some(2), "the path '$SCOG_TEST_OUTSIDE/x' expands to '/etc/x' outside the home dir".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_includes() {
+        let dir = env::temp_dir().join(format!("scog_test_includes_{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("groups")).unwrap();
+        let config = dir.join("config.yaml");
+        fs::write(&config, "profiles:\n  server:\n    sections:\n    - .ssh/config\n    tags:\n    - tmux\ninclude:\n- groups/work.yaml\nsections:\n- path: .bashrc\n").unwrap();
+        fs::write(dir.join("groups/work.yaml"), "include:\n- server.yaml\nsections:\n- path: .ssh/config\n").unwrap();
+        fs::write(dir.join("groups/server.yaml"), "sections:\n- path: .tmux.conf\n  tags:\n  - tmux\n").unwrap();
+
+        let paths: Vec<PathBuf> = Config::new(&config).unwrap().sections().iter().map(|section| section.path()).collect();
+        assert_eq!(paths, vec![PathBuf::from(".bashrc"), PathBuf::from(".ssh/config"), PathBuf::from(".tmux.conf")]);
+
+        // Profiles can use the sections and tags of the included files
+        assert!(Config::check(&config).unwrap().is_empty());
+
+        // Problems are reported in the included file
+        fs::write(dir.join("groups/server.yaml"), "include:\n- work.yaml\nsections:\n- path: .bashrc\n").unwrap();
+        let diagnostics: Vec<(PathBuf, Option<usize>, String)> = Config::check(&config).unwrap().into_iter()
+            .map(|diagnostic| (diagnostic.file, diagnostic.line, diagnostic.message))
+            .collect();
+        assert_eq!(diagnostics, vec![
+            (dir.join("groups/server.yaml"), Some(2), "the include 'work.yaml' is a cycle: groups/work.yaml -> groups/server.yaml -> groups/work.yaml".to_string()),
+            (dir.join("groups/server.yaml"), Some(4), "the path '.bashrc' is already listed at line 10 in config.yaml".to_string()),
+            (config.clone(), Some(6), "the profile 'server' uses the tag 'tmux' that no section has".to_string()),
+        ]);
+        assert!(Config::new(&config).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
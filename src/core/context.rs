@@ -1,20 +1,48 @@
+use core::report::Report;
 use core::repository::Repository;
+use std::path::PathBuf;
+use utils::output::Output;
+use utils::output::Verbosity;
+use utils::progress::Progress;
 
 pub struct Context {
+    output: Output,
+    verbosity: Verbosity,
+    repository_dir: Option<PathBuf>,
     repository: Option<Repository>,
 }
 
 impl Context {
-    pub fn new() -> Context {
+    pub fn new(output: Output, verbosity: Verbosity, repository_dir: Option<PathBuf>) -> Context {
         Context {
+            output,
+            verbosity,
+            repository_dir,
             repository: None,
         }
     }
 
+    pub fn output(&self) -> Output {
+        self.output
+    }
+
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
     pub fn repository(&mut self) -> &mut Repository {
         if self.repository.is_none() {
-            self.repository = Some(Repository::new());
+            let progress = Progress::detect(self.output, self.verbosity);
+            self.repository = Some(Repository::new(self.repository_dir.clone(), progress));
         }
         self.repository.as_mut().unwrap()
     }
-}
\ No newline at end of file
+
+    /// Everything done by the executed command
+    pub fn report(&self) -> Report {
+        match self.repository {
+            Some(ref repository) => repository.report().clone(),
+            None => Report::default(),
+        }
+    }
+}
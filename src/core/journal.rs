@@ -0,0 +1,106 @@
+use core::state;
+use std::path::Path;
+use std::path::PathBuf;
+use utils::error::Error;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Operation {
+    Pull,
+    Push,
+    Checkout,
+    CheckoutFile,
+}
+
+impl Operation {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Operation::Pull => "pull",
+            Operation::Push => "push",
+            Operation::Checkout => "checkout",
+            Operation::CheckoutFile => "checkout-file",
+        }
+    }
+}
+
+/// A pull or push executed on the repository
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Entry {
+    pub operation: Operation,
+    pub time: String,
+    pub branch: String,
+    /// Commit pointed by the branch before the operation
+    pub old_oid: String,
+    /// Commit pointed by the branch after the operation
+    pub new_oid: String,
+    pub backup_branch: Option<String>,
+    /// Local files written by the operation, relative to the home dir
+    pub written: Vec<PathBuf>,
+    /// Local files that did not exist before the operation, relative to the home dir
+    pub created: Vec<PathBuf>,
+    /// The push has only been committed locally and queued
+    #[serde(default)]
+    pub queued: bool,
+    #[serde(default)]
+    pub undone: bool,
+}
+
+/// List of all the operations
+#[derive(Serialize, Deserialize, Default)]
+pub struct Journal {
+    entries: Vec<Entry>,
+}
+
+impl Journal {
+    pub fn load(file: &Path) -> Result<Journal, Error> {
+        state::load(file)
+    }
+
+    pub fn save(&self, file: &Path) -> Result<(), Error> {
+        state::save(self, file)
+    }
+
+    pub fn push(&mut self, entry: Entry) {
+        self.entries.push(entry);
+    }
+
+    /// Return the last operation that has not been undone yet
+    pub fn last_mut(&mut self) -> Option<&mut Entry> {
+        self.entries.iter_mut().rev().find(|entry| !entry.undone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Entry;
+    use super::Journal;
+    use super::Operation;
+    use std::path::PathBuf;
+
+    fn entry(operation: Operation) -> Entry {
+        Entry {
+            operation,
+            time: String::from("2026-10-18_10-00-00_000"),
+            branch: String::from("master"),
+            old_oid: String::from("a"),
+            new_oid: String::from("b"),
+            backup_branch: None,
+            written: vec![PathBuf::from(".bashrc")],
+            created: vec![],
+            queued: false,
+            undone: false,
+        }
+    }
+
+    #[test]
+    fn test_last_mut() {
+        let mut journal = Journal::default();
+        assert!(journal.last_mut().is_none());
+
+        journal.push(entry(Operation::Pull));
+        journal.push(entry(Operation::Push));
+        journal.last_mut().unwrap().undone = true;
+
+        assert_eq!(journal.last_mut().unwrap().operation, Operation::Pull);
+    }
+}
@@ -0,0 +1,113 @@
+use core::config::Section;
+use git2::Delta;
+use std::path::PathBuf;
+
+/// Template used when the config has no commit_message
+pub const DEFAULT_TEMPLATE: &str = "{changes} ({user}@{hostname})";
+
+/// Describe the staged changes grouped by section, like "modified .bashrc, .vimrc; added .gitconfig",
+/// staged files outside any section are listed by their own path
+pub fn describe(sections: &[Section], staged: &[(PathBuf, Delta)]) -> String {
+    let mut added: Vec<PathBuf> = vec![];
+    let mut modified: Vec<PathBuf> = vec![];
+    let mut removed: Vec<PathBuf> = vec![];
+
+    // Collect how each section changed
+    let mut paths: Vec<(PathBuf, Vec<Delta>)> = vec![];
+    for (file, delta) in staged {
+        let path = sections.iter()
+            .map(|section| section.repo_path())
+            .find(|path| file.starts_with(path))
+            .unwrap_or_else(|| file.clone());
+        match paths.iter_mut().find(|(_path, _)| *_path == path) {
+            Some((_, deltas)) => deltas.push(*delta),
+            None => paths.push((path, vec![*delta])),
+        }
+    }
+
+    // A section is added or removed only if all its files are
+    for (path, deltas) in paths {
+        if deltas.iter().all(|delta| *delta == Delta::Added) {
+            added.push(path);
+        } else if deltas.iter().all(|delta| *delta == Delta::Deleted) {
+            removed.push(path);
+        } else {
+            modified.push(path);
+        }
+    }
+
+    let mut groups: Vec<String> = vec![];
+    for (name, paths) in [("modified", modified), ("added", added), ("removed", removed)].iter() {
+        if !paths.is_empty() {
+            let paths: Vec<String> = paths.iter().map(|path| path.to_string_lossy().to_string()).collect();
+            groups.push(format!("{} {}", name, paths.join(", ")));
+        }
+    }
+
+    match groups.is_empty() {
+        true => "no changes".to_string(),
+        false => groups.join("; "),
+    }
+}
+
+/// Replace {changes}, {hostname}, {user} and {date} in the template
+pub fn render(template: &str, changes: &str, hostname: &str, user: &str, date: &str) -> String {
+    template
+        .replace("{changes}", changes)
+        .replace("{hostname}", hostname)
+        .replace("{user}", user)
+        .replace("{date}", date)
+}
+
+/// Append the trailer with the machine that created the commit
+pub fn with_host(message: &str, hostname: &str) -> String {
+    format!("{}\n\nHost: {}", message, hostname)
+}
+
+/// Return the machine that created the commit from its trailer
+pub fn host(message: &str) -> Option<String> {
+    message.lines().rev()
+        .find(|line| line.starts_with("Host: "))
+        .map(|line| line["Host: ".len()..].trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::describe;
+    use super::host;
+    use super::with_host;
+    use super::render;
+    use super::DEFAULT_TEMPLATE;
+    use core::config::Section;
+    use git2::Delta;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_describe() {
+        let sections = vec![Section::new(".bashrc".to_string()), Section::new(".config/nvim".to_string())];
+        let staged = vec![
+            (PathBuf::from(".bashrc"), Delta::Modified),
+            (PathBuf::from(".config/nvim/init.vim"), Delta::Added),
+            (PathBuf::from(".config/nvim/lua/plugins.lua"), Delta::Added),
+            (PathBuf::from("config.yaml"), Delta::Modified),
+        ];
+
+        assert_eq!(describe(&sections, &staged), "modified .bashrc, config.yaml; added .config/nvim");
+        assert_eq!(describe(&sections, &[]), "no changes");
+    }
+
+    #[test]
+    fn test_render() {
+        let message = render(DEFAULT_TEMPLATE, "modified .bashrc", "laptop", "davide", "2026-10-18");
+        assert_eq!(message, "modified .bashrc (davide@laptop)");
+        assert_eq!(render("{date} {hostname}", "", "laptop", "", "2026-10-18"), "2026-10-18 laptop");
+    }
+
+    #[test]
+    fn test_host() {
+        let message = with_host("modified .bashrc", "laptop");
+        assert_eq!(message, "modified .bashrc\n\nHost: laptop");
+        assert_eq!(host(message.as_str()), Some("laptop".to_string()));
+        assert_eq!(host("Merge remote changes into master"), None);
+    }
+}
@@ -1,4 +1,10 @@
 pub mod config;
 pub mod context;
+pub mod journal;
+pub mod message;
+pub mod profile;
+pub mod queue;
+pub mod report;
+pub mod state;
 pub mod validate;
 pub mod repository;
\ No newline at end of file
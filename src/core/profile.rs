@@ -0,0 +1,29 @@
+use core::state;
+use std::path::Path;
+use utils::error::Error;
+
+/// Profile used on this machine
+#[derive(Serialize, Deserialize, Default)]
+pub struct ActiveProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+}
+
+impl ActiveProfile {
+    pub fn load(file: &Path) -> Result<ActiveProfile, Error> {
+        state::load(file)
+    }
+
+    pub fn save(&self, file: &Path) -> Result<(), Error> {
+        state::save(self, file)
+    }
+
+    /// The active profile, None if all the sections are used
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn set(&mut self, name: Option<&str>) {
+        self.name = name.map(|name| name.to_string());
+    }
+}
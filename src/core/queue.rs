@@ -0,0 +1,51 @@
+use core::state;
+use std::path::Path;
+use utils::error::Error;
+
+/// Working branches committed while offline that still have to be pushed
+#[derive(Serialize, Deserialize, Default)]
+pub struct Queue {
+    branches: Vec<String>,
+}
+
+impl Queue {
+    pub fn load(file: &Path) -> Result<Queue, Error> {
+        state::load(file)
+    }
+
+    pub fn save(&self, file: &Path) -> Result<(), Error> {
+        state::save(self, file)
+    }
+
+    pub fn contains(&self, branch_name: &str) -> bool {
+        self.branches.iter().any(|branch| branch == branch_name)
+    }
+
+    pub fn add(&mut self, branch_name: &str) {
+        if !self.contains(branch_name) {
+            self.branches.push(branch_name.to_string());
+        }
+    }
+
+    pub fn remove(&mut self, branch_name: &str) {
+        self.branches.retain(|branch| branch != branch_name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Queue;
+
+    #[test]
+    fn test_add_and_remove() {
+        let mut queue = Queue::default();
+        queue.add("master");
+        queue.add("master");
+        queue.add("laptop");
+        assert_eq!(queue.branches, vec!["master", "laptop"]);
+
+        queue.remove("master");
+        assert!(!queue.contains("master"));
+        assert!(queue.contains("laptop"));
+    }
+}
@@ -0,0 +1,156 @@
+use std::path::PathBuf;
+use utils::error::Error;
+
+/// A commit created by scog
+#[derive(Serialize, Clone)]
+pub struct Commit {
+    pub branch: String,
+    pub oid: String,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    /// The local file differs from the repository file
+    Modified,
+    /// The file exists only on the local disk
+    LocalOnly,
+    /// The file exists only in the repository
+    RepositoryOnly,
+}
+
+impl State {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            State::Modified => "modified",
+            State::LocalOnly => "local only",
+            State::RepositoryOnly => "repository only",
+        }
+    }
+}
+
+/// A file listed in config that is not in sync between local disk and repository
+#[derive(Serialize, Clone)]
+pub struct Change {
+    pub path: PathBuf,
+    pub state: State,
+}
+
+/// A section not copied on this machine
+#[derive(Serialize, Clone)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Status {
+    pub branch: String,
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+    pub dirty: bool,
+    /// The branch has commits queued while offline
+    pub queued: bool,
+    /// Profile active on this machine, only its sections are compared
+    pub profile: Option<String>,
+    pub changes: Vec<Change>,
+    /// Sections outside the profile or whose conditions are not met
+    pub skipped: Vec<Skipped>,
+}
+
+/// The remote branch tracked by a working branch
+#[derive(Serialize, Clone)]
+pub struct Tracking {
+    pub branch: String,
+    pub upstream: Option<String>,
+}
+
+/// A commit listed by log
+#[derive(Serialize, Clone)]
+pub struct LogEntry {
+    pub oid: String,
+    pub author: String,
+    pub email: String,
+    /// Machine that created the commit, from the commit message trailer
+    pub host: Option<String>,
+    pub time: String,
+    pub summary: String,
+    /// Changed files relative to the repository
+    pub files: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
+}
+
+/// A problem found in a config file
+#[derive(Serialize, Clone)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+/// Everything done by a command, printed at the end of its execution
+#[derive(Serialize, Clone, Default)]
+pub struct Report {
+    /// The branch checked out by the command
+    pub branch: Option<String>,
+    /// Files copied from local disk to repository, relative to the repository
+    pub copied_to_repository: Vec<PathBuf>,
+    /// Files copied from repository to local disk, relative to the home dir
+    pub copied_to_local: Vec<PathBuf>,
+    /// Existing local files replaced (or that would be replaced in preview) by the repository files
+    pub replaced_local: Vec<PathBuf>,
+    /// Commit the local files have been restored from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restored_from: Option<String>,
+    /// Nothing has been written to local disk
+    #[serde(skip)]
+    pub preview: bool,
+    pub commits: Vec<Commit>,
+    pub backup_branches: Vec<String>,
+    /// Pushed references in the form REMOTE:REFERENCE
+    pub pushed_refs: Vec<String>,
+    /// Remotes the push failed on, the other remotes are still pushed
+    pub failed_pushes: Vec<FailedPush>,
+    /// Branches committed while offline, pushed by the next online push or sync
+    pub queued: Vec<String>,
+    /// Profile activated on this machine
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    pub status: Option<Status>,
+    pub tracking: Vec<Tracking>,
+    pub log: Vec<LogEntry>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A push to a single remote that failed
+#[derive(Serialize, Clone)]
+pub struct FailedPush {
+    pub remote: String,
+    pub error: ErrorReport,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ErrorReport {
+    pub kind: String,
+    pub code: i32,
+    pub message: String,
+    pub path: Option<PathBuf>,
+    pub branch: Option<String>,
+    pub causes: Vec<String>,
+}
+
+impl From<&Error> for ErrorReport {
+    fn from(error: &Error) -> Self {
+        ErrorReport {
+            kind: error.kind().as_str().to_string(),
+            code: error.kind().exit_code(),
+            message: error.message().to_string(),
+            path: error.path().map(|path| path.to_path_buf()),
+            branch: error.branch().map(|branch| branch.to_string()),
+            causes: error.causes(),
+        }
+    }
+}
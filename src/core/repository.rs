@@ -1,51 +1,146 @@
+use core::config;
 use core::config::Config;
+use core::config::Format;
+use core::config::Section;
 use utils::git::Helper;
+use utils::git::Identity;
 use utils::error::Error;
+use utils::error::Kind;
 use std::env;
+use std::fs;
 use std::path::PathBuf;
 use core::validate;
+use core::journal::Entry;
+use core::journal::Journal;
+use core::journal::Operation;
+use core::message;
+use core::profile::ActiveProfile;
+use core::queue::Queue;
+use core::report::Change;
+use core::report::Commit;
+use core::report::LogEntry;
+use core::report::FailedPush;
+use core::report::Report;
+use core::report::Skipped;
+use core::report::State;
+use core::report::Status;
+use core::report::ErrorReport;
+use core::report::Tracking;
+use git2::Oid;
+use serde_json;
 use utils::backup::backup_branch_name;
+use utils::backup::is_backup;
+use utils::backup::is_backup_of;
+use utils::block;
 use utils::copy::copy;
+use utils::copy::plan;
+use utils::copy;
+use utils::host;
+use utils::merge;
+use utils::progress::Progress;
+use utils::transaction::Transaction;
+use utils::time;
 use utils::time::now_to_string;
 use std::path::Path;
 
+/// Local files changed while updating the working branch
+#[derive(Default)]
+struct Update {
+    backup_branch_name: Option<String>,
+    /// Written files relative to home_dir
+    written: Vec<PathBuf>,
+    /// Files that did not exist before, relative to home_dir
+    created: Vec<PathBuf>,
+}
+
 pub struct Repository {
     home_dir: PathBuf,
     repository_dir: PathBuf,
-    config_file: PathBuf,
+    journal_file: PathBuf,
+    queue_file: PathBuf,
+    profile_file: PathBuf,
     config: Option<Config>,
     git: Option<Helper>,
+    progress: Progress,
+    report: Report,
 }
 
 impl Repository {
-    pub fn new() -> Repository {
+    /// Open the repository in repository_dir, by default $HOME/.scog
+    pub fn new(repository_dir: Option<PathBuf>, progress: Progress) -> Repository {
         let home_dir = env::home_dir().unwrap();
-        let repository_dir = home_dir.join(".scog/");
-        let config_file = repository_dir.join("config.yaml");
+        let repository_dir = repository_dir.unwrap_or_else(|| home_dir.join(".scog/"));
+        let journal_file = repository_dir.join(".git/scog/journal.yaml");
+        let queue_file = repository_dir.join(".git/scog/queue.yaml");
+        let profile_file = repository_dir.join(".git/scog/profile.yaml");
 
         Repository {
             home_dir,
             repository_dir,
-            config_file,
+            journal_file,
+            queue_file,
+            profile_file,
             config: None,
             git: None,
+            progress,
+            report: Report::default(),
         }
     }
 
+    /// Everything done by the executed operations
+    pub fn report(&self) -> &Report {
+        &self.report
+    }
+
     fn free_config(&mut self) {
         self.config = None;
     }
 
+    /// The config file of the working branch, it can be yaml, toml or json
+    fn config_file(&self) -> PathBuf {
+        config::find(&self.repository_dir)
+    }
+
     fn config(&mut self) -> Result<&Config, Error> {
         if self.config.is_none() {
-            self.config = Some(Config::new(&self.config_file)?);
+            self.config = Some(Config::new(&self.config_file())?);
         }
         Ok(self.config.as_ref().unwrap())
     }
 
+    /// Sections copied on this machine: the ones of the active profile, all of them without a profile,
+    /// whose conditions are met
+    fn sections(&mut self) -> Result<Vec<Section>, Error> {
+        Ok(self.select_sections()?.0)
+    }
+
+    /// Split the sections in the ones copied on this machine and the skipped ones with the reason
+    fn select_sections(&mut self) -> Result<(Vec<Section>, Vec<Skipped>), Error> {
+        let profile = ActiveProfile::load(&self.profile_file)?;
+        let in_profile = self.config()?.profile_sections(profile.name())?;
+
+        let mut selected: Vec<Section> = vec![];
+        let mut skipped: Vec<Skipped> = vec![];
+        for section in self.config()?.sections() {
+            // Never write outside the home dir or to a path with unset variables, even if the config has not been checked
+            if let Some(problem) = section.path_problem() {
+                return Err(Error::new(Kind::Config, problem).with_path(&self.config_file()));
+            }
+            let reason = match in_profile.iter().any(|other| other.path() == section.path()) {
+                true => section.unmet(),
+                false => Some(format!("not in the profile {}", profile.name().unwrap_or_default())),
+            };
+            match reason {
+                Some(reason) => skipped.push(Skipped { path: section.path(), reason }),
+                None => selected.push(section),
+            }
+        }
+        Ok((selected, skipped))
+    }
+
     fn git(&mut self) -> Result<&Helper, Error> {
         if self.git.is_none() {
-            self.git = Some(Helper::new(&self.repository_dir)?);
+            self.git = Some(Helper::new(&self.repository_dir, self.progress)?);
         }
         Ok(self.git.as_ref().unwrap())
     }
@@ -56,9 +151,36 @@ impl Repository {
         let home_dir = self.home_dir.clone();
 
         let mut copied: Vec<PathBuf> = vec![];
-        for section in self.config()?.sections() {
+        for section in self.sections()? {
             let source = home_dir.join(section.path());
-            let destination = repository_dir.join(section.path());
+            let destination = repository_dir.join(section.repo_path());
+
+            // Only the block is stored in the repository, nothing if the local file has no markers
+            if let Some(name) = section.block_name() {
+                if let Some(extracted) = block::read(&source)?.and_then(|content| block::extract(content.as_str(), name.as_str())) {
+                    if let Some(parent) = destination.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&destination, extracted).map_err(|e| Error::from(e).with_path(&destination))?;
+                    copied.push(destination);
+                }
+                continue;
+            }
+
+            // The overridden keys get back their shared value so the repository never sees the local ones
+            if section.local_overrides() {
+                exclude_local_overrides(&repository_dir)?;
+                for (source, destination) in plan(source.as_path(), destination.as_path())? {
+                    let content = repository_content(&repository_dir, &source, &destination)?;
+                    if let Some(parent) = destination.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&destination, content).map_err(|e| Error::from(e).with_path(&destination))?;
+                    copied.push(destination);
+                }
+                continue;
+            }
+
             let mut _copied = copy(source.as_path(), destination.as_path())?;
             copied.append(&mut _copied)
         }
@@ -66,16 +188,76 @@ impl Repository {
         Ok(copied)
     }
 
-    /// Copy files listed in config from repository_dir to home_dir
-    fn copy_to_local(&mut self) -> Result<(), Error> {
+    /// Return the existing local files that differ from the repository files, relative to home_dir
+    fn local_files_to_replace(&mut self) -> Result<Vec<PathBuf>, Error> {
         let repository_dir = self.repository_dir.clone();
         let home_dir = self.home_dir.clone();
-        for section in self.config()?.sections() {
-            let source = repository_dir.join(section.path());
+
+        let mut replaced: Vec<PathBuf> = vec![];
+        for section in self.sections()? {
+            let source = repository_dir.join(section.repo_path());
             let destination = home_dir.join(section.path());
-            copy(source.as_path(), destination.as_path())?;
+            if let Some(name) = section.block_name() {
+                if let (Some(content), Some(spliced)) = (block::read(&destination)?, spliced(&destination, &source, name.as_str())?) {
+                    if content != spliced {
+                        replaced.push(destination.strip_prefix(&home_dir)?.to_path_buf());
+                    }
+                }
+                continue;
+            }
+            for (source, destination) in plan(source.as_path(), destination.as_path())? {
+                if destination.exists() && local_content(&repository_dir, &source, section.local_overrides())? != fs::read(&destination)? {
+                    replaced.push(destination.strip_prefix(&home_dir)?.to_path_buf());
+                }
+            }
         }
-        Ok(())
+        Ok(replaced)
+    }
+
+    /// Copy files listed in config from repository_dir to home_dir,
+    /// all copies are planned before writing and if one of them fails the home_dir is rolled back.
+    /// Return the written files and the created files relative to home_dir
+    fn copy_to_local(&mut self) -> Result<(Vec<PathBuf>, Vec<PathBuf>), Error> {
+        let repository_dir = self.repository_dir.clone();
+        let home_dir = self.home_dir.clone();
+
+        let mut operations: Vec<copy::Operation> = vec![];
+        let mut writes: Vec<(PathBuf, Vec<u8>)> = vec![];
+        for section in self.sections()? {
+            let source = repository_dir.join(section.repo_path());
+            let destination = home_dir.join(section.path());
+
+            // The block is spliced in the local file, creating the markers if missing
+            if let Some(name) = section.block_name() {
+                if let Some(spliced) = spliced(&destination, &source, name.as_str())? {
+                    writes.push((destination, spliced.into_bytes()));
+                }
+                continue;
+            }
+
+            // Files with local overrides are written with the overrides on top
+            for (source, destination) in plan(source.as_path(), destination.as_path())? {
+                if section.local_overrides() && overrides_file(&repository_dir, &source)?.is_file() {
+                    let content = local_content(&repository_dir, &source, true)?;
+                    writes.push((destination, content));
+                } else {
+                    operations.push((source, destination));
+                }
+            }
+        }
+
+        let created: Vec<PathBuf> = operations.iter()
+            .map(|(_, destination)| destination)
+            .chain(writes.iter().map(|(destination, _)| destination))
+            .filter(|destination| !destination.exists())
+            .map(|destination| destination.strip_prefix(&home_dir).map(|path| path.to_path_buf()))
+            .collect::<Result<_, _>>()?;
+
+        let written: Vec<PathBuf> = Transaction::new().apply(&operations, &writes)?.iter()
+            .map(|destination| destination.strip_prefix(&home_dir).map(|path| path.to_path_buf()))
+            .collect::<Result<_, _>>()?;
+
+        Ok((written, created))
     }
 
     /// Stage all the passed files (add to index)
@@ -91,38 +273,374 @@ impl Repository {
     /// Copy files from local disk to repository and stage all files
     /// return than true if repository is dirty or false if there si nothing to commit
     fn copy_to_repository_and_stage_files(&mut self) -> Result<bool, Error> {
+        self.copy_to_repository_and_stage()?;
+        self.git()?.is_dirty()
+    }
+
+    /// Copy files from local disk to repository and stage all files
+    fn copy_to_repository_and_stage(&mut self) -> Result<(), Error> {
         let copied = self.copy_to_repository()?;
         self.stage_files(copied.iter().map(|path| path.as_path()).collect())?;
-        Ok(self.git()?.is_dirty()?)
+        for path in copied {
+            let path = path.strip_prefix(&self.repository_dir)?.to_path_buf();
+            if !self.report.copied_to_repository.contains(&path) {
+                self.report.copied_to_repository.push(path);
+            }
+        }
+        Ok(())
     }
 
 
+    /// Author and signing key of the commits from config
+    fn identity(&mut self) -> Result<Identity, Error> {
+        let config = self.config()?;
+        Ok(Identity {
+            name: config.author().and_then(|author| author.name()).map(|name| name.to_string()),
+            email: config.author().and_then(|author| author.email()).map(|email| email.to_string()),
+            signing: config.signing().map(|signing| (signing.format(), signing.key().to_string())),
+        })
+    }
+
+    /// Describe the staged changes with the message template
+    fn commit_message(&mut self, template: &str) -> Result<String, Error> {
+        let staged = self.git()?.staged_changes()?;
+        let changes = message::describe(&self.config()?.sections(), &staged);
+        Ok(message::render(template, changes.as_str(), host::hostname().as_str(), host::user().as_str(), now_to_string().as_str()))
+    }
+
+    /// Commit the staged files with the identity in config, the message gets the host trailer
+    fn commit(&mut self, message: &str) -> Result<Oid, Error> {
+        let identity = self.identity()?;
+        let message = message::with_host(message, host::hostname().as_str());
+        self.git()?.commit(message.as_str(), &identity)
+    }
+
     /// Copy local file the repository and if there are changes it commit them to
-    /// the backup branch and clean up again the repository
-    fn backup_local_files(&mut self, branch_name: &str) -> Result<(), Error> {
+    /// the backup branch and clean up again the repository, return the backup branch name if created
+    fn backup_local_files(&mut self, branch_name: &str) -> Result<Option<String>, Error> {
         if self.copy_to_repository_and_stage_files()? {
-            self.git()?.branch(backup_branch_name(branch_name).as_str())?;
-            self.git()?.commit(now_to_string().as_str())?;
+            let backup_branch_name = backup_branch_name(branch_name);
+            self.git()?.branch(backup_branch_name.as_str())?;
+            let template = format!("Backup local files of {}: {}", branch_name, message::DEFAULT_TEMPLATE);
+            let message = self.commit_message(template.as_str())?;
+            let oid = self.commit(message.as_str())?;
             self.git()?.checkout_branch(branch_name)?;
+            self.report.backup_branches.push(backup_branch_name.clone());
+            self.report.commits.push(Commit { branch: backup_branch_name.clone(), oid: oid.to_string() });
+            Ok(Some(backup_branch_name))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Select the remote to fetch and push: the passed one, the one tracked by the branch,
+    /// the one in config or the first one
+    fn remote_name(&mut self, branch_name: &str, remote: Option<&str>) -> Result<Option<String>, Error> {
+        if let Some(remote) = remote {
+            return Ok(Some(remote.to_string()));
+        }
+        if let Some(remote) = self.git()?.tracked_remote(branch_name)? {
+            return Ok(Some(remote));
+        }
+        if let Some(remote) = self.config()?.remote() {
+            return Ok(Some(remote.to_string()));
         }
+        self.git()?.default_remote(branch_name)
+    }
+
+    /// Like remote_name but fail if the repository has no remote
+    fn required_remote_name(&mut self, branch_name: &str, remote: Option<&str>) -> Result<String, Error> {
+        match self.remote_name(branch_name, remote)? {
+            Some(remote) => Ok(remote),
+            None => Err(Error::new(Kind::Config, "there is no remote to fetch and push, add one with 'git remote add'".to_string()).with_branch(branch_name)),
+        }
+    }
+
+    /// Push the branch to the remote and to the push_remotes in config, all the remotes are
+    /// tried and the push fails if at least one of them failed
+    fn push_branch(&mut self, branch_name: &str, remote_name: &str) -> Result<(), Error> {
+        let mut remotes = vec![remote_name.to_string()];
+        for remote in self.config()?.push_remotes() {
+            if !remotes.contains(remote) {
+                remotes.push(remote.clone());
+            }
+        }
+
+        let mut failed: Vec<Error> = vec![];
+        for remote in remotes {
+            match self.git()?.push(branch_name, remote.as_str()) {
+                Ok(reference) => self.report.pushed_refs.push(reference),
+                Err(error) => {
+                    self.report.failed_pushes.push(FailedPush { remote, error: ErrorReport::from(&error) });
+                    failed.push(error);
+                }
+            }
+        }
+
+        if failed.is_empty() {
+            return Ok(());
+        }
+        let remotes: Vec<&str> = self.report.failed_pushes.iter().map(|failed| failed.remote.as_str()).collect();
+        let message = format!("push failed on remote(s): {}", remotes.join(", "));
+        let error = failed.remove(0);
+        Err(Error::new(error.kind(), message).with_branch(branch_name).with_source(error))
+    }
+
+    /// Make an absolute path relative to home_dir, relative paths are already relative to it
+    fn relative_to_home(&self, path: &Path) -> Result<PathBuf, Error> {
+        match path.is_absolute() {
+            true => path.strip_prefix(&self.home_dir)
+                .map(|path| path.to_path_buf())
+                .map_err(|_| Error::new(Kind::Usage, "the path is not inside the home dir".to_string()).with_path(path)),
+            false => Ok(path.to_path_buf()),
+        }
+    }
+
+    /// Create a new repository with a config listing the passed paths and commit the config
+    /// and the listed files, if remote is passed push the branch to it and track it
+    pub fn init(&mut self, remote: Option<&str>, paths: &[PathBuf]) -> Result<(), Error> {
+        if self.repository_dir.exists() && fs::read_dir(&self.repository_dir)?.next().is_some() {
+            return Err(Error::new(Kind::Other, "the repository path already exists and is not empty".to_string()).with_path(&self.repository_dir));
+        }
+
+        // Sections are relative to home_dir
+        let mut sections: Vec<Section> = vec![];
+        for path in paths {
+            let path = self.relative_to_home(path)?;
+            sections.push(Section::new(path.to_string_lossy().to_string()));
+        }
+
+        self.git = Some(Helper::init(&self.repository_dir, self.progress)?);
+
+        // Write the config and copy the listed files
+        let config_file = self.config_file();
+        Config::from_sections(sections).save(&config_file)?;
+        self.free_config();
+        let config_path = config_file.strip_prefix(&self.repository_dir)?.to_path_buf();
+        self.git()?.add(&config_path)?;
+        self.copy_to_repository_and_stage()?;
+
+        // Create the initial commit
+        let oid = self.commit("Initialize scog repository")?;
+        let branch_name = self.git()?.get_current_branch_name()?;
+        self.report.commits.push(Commit { branch: branch_name.clone(), oid: oid.to_string() });
+        self.report.branch = Some(branch_name.clone());
+
+        if let Some(remote) = remote {
+            self.git()?.add_remote("origin", remote)?;
+            self.push_branch(branch_name.as_str(), "origin")?;
+            self.git()?.set_upstream(branch_name.as_str(), "origin", branch_name.as_str())?;
+        }
+
         Ok(())
     }
 
-    pub fn clone(&self, repo: &str) -> Result<(), Error> {
-        Helper::clone(repo, &self.repository_dir)
+    /// Clone the remote repository and checkout the passed branch or the default one,
+    /// if apply is true backup the local files and copy the branch files to local,
+    /// if preview is also true only report the local files that would be replaced
+    pub fn clone(&mut self, repo: &str, branch_name: Option<&str>, apply: bool, preview: bool) -> Result<(), Error> {
+        Helper::clone(repo, &self.repository_dir, self.progress)?;
+
+        if let Some(branch_name) = branch_name {
+            validate::branch(branch_name)?;
+            self.git()?.checkout_branch(branch_name)?;
+        }
+
+        let branch_name = self.git()?.get_current_branch_name()?;
+        self.report.branch = Some(branch_name.clone());
+
+        if !apply {
+            return Ok(());
+        }
+
+        validate::branch(branch_name.as_str())?;
+
+        // Check the config before copying any file
+        self.config_check()?;
+
+        // Existing local files that will be replaced
+        let mut replaced = self.local_files_to_replace()?;
+        self.report.replaced_local.append(&mut replaced);
+
+        if preview {
+            self.report.preview = true;
+            return Ok(());
+        }
+
+        let oid = self.git()?.head_oid()?;
+        let update = self.apply(branch_name.as_str(), oid)?;
+        self.record(Operation::Pull, branch_name.as_str(), oid, update, false)
     }
 
-    pub fn checkout(&mut self, branch_name: &str) -> Result<(), Error> {
+    /// Checkout the passed branch, if it does not exist create it from the base branch
+    /// (by default the current one), push it and track it. Then backup the local files and
+    /// copy the branch files to local
+    pub fn checkout(&mut self, branch_name: &str, base_name: Option<&str>, remote: Option<&str>) -> Result<(), Error> {
+
+        // Check if the name is not reserved for backups
+        validate::branch_name(branch_name)?;
+
+        // Check if repository is not dirty
+        validate::repository(self.git()?)?;
+
         // Fetch remote data
-        self.git()?.fetch()?;
+        let current_name = self.git()?.get_current_branch_name()?;
+        let remote = self.remote_name(base_name.unwrap_or(current_name.as_str()), remote)?;
+        if let Some(ref remote) = remote {
+            self.git()?.fetch(remote.as_str())?;
+        }
 
-        // Checkout the branch
-        self.git()?.checkout_branch(branch_name)?;
+        if self.git()?.branch_exists(branch_name)? {
+            if base_name.is_some() {
+                return Err(Error::new(Kind::Usage, "the branch already exists, --from can be used only to create a new branch".to_string()).with_branch(branch_name));
+            }
 
-        Ok(())
+            // Checkout the branch
+            self.git()?.checkout_branch(branch_name)?;
+        } else {
+            let base_name = base_name.unwrap_or(current_name.as_str());
+
+            // Create the branch and publish it on the selected remote
+            self.git()?.create_branch(branch_name, base_name)?;
+            if let Some(remote) = remote {
+                self.push_branch(branch_name, remote.as_str())?;
+                self.git()?.set_upstream(branch_name, remote.as_str(), branch_name)?;
+            }
+        }
+
+        // The branch can have a different config, if it has problems go back to the previous branch
+        self.free_config();
+        if let Err(error) = self.config_check() {
+            self.git()?.checkout_branch(current_name.as_str())?;
+            self.free_config();
+            return Err(error.with_branch(branch_name));
+        }
+
+        self.report.branch = Some(branch_name.to_string());
+
+        // Backup local files and copy the branch files
+        let oid = self.git()?.head_oid()?;
+        let update = self.apply(branch_name, oid)?;
+        self.record(Operation::Checkout, branch_name, oid, update, false)
+    }
+
+    /// Fast forward the branch (or merge it if the branches diverged), backup local files and copy files from repository
+    /// to local, if copying fails the local files are restored and the branch is reset to keep them in sync
+    fn update(&mut self, branch_name: &str, remote_name: &str) -> Result<Update, Error> {
+
+        // Remember the current commit to be able to roll back
+        let previous_oid = self.git()?.head_oid()?;
+
+        // Fast forward or, if the branches diverged, merge branch
+        let identity = self.identity()?;
+        let structured = self.config()?.structured_paths();
+        if let Some(oid) = self.git()?.pull(branch_name, remote_name, Some(&identity), &structured)? {
+            self.report.commits.push(Commit { branch: branch_name.to_string(), oid: oid.to_string() });
+        }
+
+        // Config must be reloaded and checked before copying any file
+        self.free_config();
+        if let Err(error) = self.config_check() {
+            return Err(self.roll_back(branch_name, previous_oid, "the pulled config has problems", error));
+        }
+
+        self.apply(branch_name, previous_oid)
+    }
+
+    /// Backup local files and copy files from repository to local, if the backup or the copy
+    /// fails the local files are restored and the branch is reset to previous_oid
+    fn apply(&mut self, branch_name: &str, previous_oid: Oid) -> Result<Update, Error> {
+
+        // Backup local files, the branch may already point to the pulled commit
+        let backup_branch_name = match self.backup_local_files(branch_name) {
+            Ok(backup_branch_name) => backup_branch_name,
+            Err(error) => return Err(self.roll_back(branch_name, previous_oid, "backup of local files failed", error)),
+        };
+
+        // Copy files form repository to local, on failure local files are already restored
+        // so move back the branch to keep repository and local files in sync
+        match self.copy_to_local() {
+            Ok((written, created)) => {
+                self.report.copied_to_local.extend(written.iter().cloned());
+                Ok(Update { backup_branch_name, written, created })
+            }
+            Err(error) => {
+                let message = match backup_branch_name {
+                    Some(backup_branch_name) => format!("copy to local failed, local files are also saved in the backup branch '{}'", backup_branch_name),
+                    None => "copy to local failed".to_string(),
+                };
+                Err(self.roll_back(branch_name, previous_oid, message.as_str(), error))
+            }
+        }
+    }
+
+    /// Move the branch back to previous_oid after the error, if also the reset fails the
+    /// message reports both failures and the error keeps the first one as its source
+    fn roll_back(&mut self, branch_name: &str, previous_oid: Oid, message: &str, error: Error) -> Error {
+        let reset = self.git().and_then(|git| git.reset_branch(branch_name, previous_oid));
+        self.free_config();
+        let message = match reset {
+            Ok(()) => format!("{}, branch reset to {}", message, previous_oid),
+            Err(reset_error) => {
+                let mut reasons = vec![reset_error.to_string()];
+                reasons.append(&mut reset_error.causes());
+                format!("{}, reset of the branch to {} failed too: {}, reset it manually", message, previous_oid, reasons.join(": "))
+            }
+        };
+        Error::new(error.kind(), message).with_branch(branch_name).with_source(error)
+    }
+
+    /// Add the executed operation to the journal
+    fn record(&mut self, operation: Operation, branch_name: &str, old_oid: Oid, update: Update, queued: bool) -> Result<(), Error> {
+        let new_oid = self.git()?.head_oid()?;
+        let mut journal = Journal::load(&self.journal_file)?;
+        journal.push(Entry {
+            operation,
+            time: now_to_string(),
+            branch: branch_name.to_string(),
+            old_oid: old_oid.to_string(),
+            new_oid: new_oid.to_string(),
+            backup_branch: update.backup_branch_name,
+            written: update.written,
+            created: update.created,
+            queued,
+            undone: false,
+        });
+        journal.save(&self.journal_file)
+    }
+
+    /// Restore the local files written by the operation to the state they had before it
+    fn restore_local_files(&mut self, entry: &Entry) -> Result<(), Error> {
+        let home_dir = self.home_dir.clone();
+
+        // Local files that differed from the repository are in the backup branch,
+        // otherwise take them from the branch before the operation
+        let source_oid = match entry.backup_branch {
+            Some(ref backup_branch) => self.git()?.branch_oid(backup_branch.as_str())?,
+            None => Oid::from_str(entry.old_oid.as_str())?,
+        };
+
+        // Files are read from their path in the repository, the commits have them without the local overrides
+        let repository_dir = self.repository_dir.clone();
+        let config = self.config()?.clone();
+        let git = self.git()?;
+        Transaction::new().execute(|transaction| {
+            for path in &entry.written {
+                let destination = home_dir.join(path);
+                let repository_path = config.to_repository(path);
+                if entry.created.contains(path) {
+                    transaction.remove(destination.as_path())?;
+                } else if let Some(content) = git.read_file(source_oid, repository_path.as_path())? {
+                    let content = with_overrides(&repository_dir, &repository_dir.join(&repository_path), config.local_overrides(path), content)?;
+                    let content = with_block(destination.as_path(), config.block_name(path), content)?;
+                    transaction.write(destination.as_path(), content.as_slice())?;
+                }
+            }
+            Ok(())
+        })
     }
 
-    pub fn pull(&mut self) -> Result<(), Error> {
+    pub fn pull(&mut self, remote: Option<&str>) -> Result<(), Error> {
 
         // Get current branch name
         let branch_name = self.git()?.get_current_branch_name()?;
@@ -133,22 +651,24 @@ impl Repository {
         // Check if repository is not dirty
         validate::repository(self.git()?)?;
 
-        // Fast forward branch
-        self.git()?.pull(branch_name.as_str())?;
+        // Check the config before copying any file
+        self.config_check()?;
 
-        // Config must be reloaded
-        self.free_config();
+        // Fetch and check there is a remote branch to pull from
+        let remote_name = self.required_remote_name(branch_name.as_str(), remote)?;
+        self.git()?.fetch(remote_name.as_str())?;
+        self.git()?.ensure_pull_source(branch_name.as_str(), remote_name.as_str())?;
+        let old_oid = self.git()?.head_oid()?;
 
-        // Backup local files
-        self.backup_local_files(branch_name.as_str())?;
+        // Pull new changes and copy them to local
+        let update = self.update(branch_name.as_str(), remote_name.as_str())?;
 
-        // Copy files form repository to local
-        self.copy_to_local()?;
-
-        Ok(())
+        self.record(Operation::Pull, branch_name.as_str(), old_oid, update, false)
     }
 
-    pub fn push(&mut self) -> Result<(), Error> {
+    /// Commit local files with the passed message or the one built from the template in config and push them,
+    /// if offline is true or the remote can not be reached the commit is only queued and pushed by the next online push or sync
+    pub fn push(&mut self, remote: Option<&str>, offline: bool, message: Option<&str>) -> Result<(), Error> {
 
         // Get current branch name
         let branch_name = self.git()?.get_current_branch_name()?;
@@ -159,20 +679,745 @@ impl Repository {
         // Check if repository is not dirty
         validate::repository(self.git()?)?;
 
-        // Fetch
-        self.git()?.fetch()?;
+        // Check the config before copying any file
+        self.config_check()?;
+
+        // Fetch, if the remote can not be reached switch to offline
+        let remote_name = self.required_remote_name(branch_name.as_str(), remote)?;
+        let offline = offline || match self.git()?.fetch(remote_name.as_str()) {
+            Ok(()) => false,
+            Err(ref error) if error.kind() == Kind::Network => true,
+            Err(error) => return Err(error),
+        };
+
+        let old_oid = self.git()?.head_oid()?;
 
         // Update repository and commit changes
         if self.copy_to_repository_and_stage_files()? {
-            self.git()?.commit(now_to_string().as_str())?;
+            let message = match message {
+                Some(message) => message.to_string(),
+                None => {
+                    let template = self.config()?.commit_message().unwrap_or(message::DEFAULT_TEMPLATE).to_string();
+                    self.commit_message(template.as_str())?
+                }
+            };
+            let oid = self.commit(message.as_str())?;
+            self.report.commits.push(Commit { branch: branch_name.clone(), oid: oid.to_string() });
         }
 
+        if offline {
+            let mut queue = Queue::load(&self.queue_file)?;
+            queue.add(branch_name.as_str());
+            queue.save(&self.queue_file)?;
+            self.report.queued.push(branch_name.clone());
+            return self.record(Operation::Push, branch_name.as_str(), old_oid, Update::default(), true);
+        }
+
+        self.flush(branch_name.as_str(), remote_name.as_str(), old_oid)
+    }
+
+    /// Push the commits queued while offline, merging the remote changes first if needed
+    pub fn sync(&mut self, remote: Option<&str>) -> Result<(), Error> {
+
+        // Get current branch name
+        let branch_name = self.git()?.get_current_branch_name()?;
+
+        // Check if branch is not a backup
+        validate::branch(branch_name.as_str())?;
+
+        // Check if repository is not dirty
+        validate::repository(self.git()?)?;
+
+        // Check the config before copying any file
+        self.config_check()?;
+
+        let remote_name = self.required_remote_name(branch_name.as_str(), remote)?;
+        self.git()?.fetch(remote_name.as_str())?;
+        let old_oid = self.git()?.head_oid()?;
+
+        self.flush(branch_name.as_str(), remote_name.as_str(), old_oid)
+    }
+
+    /// Pull new changes, merging them if the branches diverged, push the branch and remove it from the queue
+    fn flush(&mut self, branch_name: &str, remote_name: &str, old_oid: Oid) -> Result<(), Error> {
+        let mut queue = Queue::load(&self.queue_file)?;
+        let queued = queue.contains(branch_name);
+
         // Pull new changes
-        self.pull()?;
+        let update = self.update(branch_name, remote_name)?;
+
+        // Push the working branch, the operation is recorded even if some remotes failed
+        // and if a remote can not be reached the branch is queued as if it was offline
+        let pushed = self.push_branch(branch_name, remote_name);
+        match pushed {
+            Ok(()) if queued => {
+                queue.remove(branch_name);
+                queue.save(&self.queue_file)?;
+            }
+            Err(ref error) if error.kind() == Kind::Network => {
+                queue.add(branch_name);
+                queue.save(&self.queue_file)?;
+                self.report.queued.push(branch_name.to_string());
+                return self.record(Operation::Push, branch_name, old_oid, update, true);
+            }
+            _ => {}
+        }
+        self.record(Operation::Push, branch_name, old_oid, update, false)?;
+        pushed
+    }
+
+    /// Compare the working branch with its upstream and the local files with the repository files
+    pub fn status(&mut self) -> Result<(), Error> {
+        let repository_dir = self.repository_dir.clone();
+        let home_dir = self.home_dir.clone();
+
+        let branch_name = self.git()?.get_current_branch_name()?;
+        let upstream = self.git()?.upstream_status(branch_name.as_str())?;
+        let dirty = self.git()?.is_dirty()?;
+        let queued = Queue::load(&self.queue_file)?.contains(branch_name.as_str());
+        let profile = ActiveProfile::load(&self.profile_file)?.name().map(|name| name.to_string());
+
+        let (sections, skipped) = self.select_sections()?;
+        let mut changes: Vec<Change> = vec![];
+        for section in sections {
+            let local = home_dir.join(section.path());
+            let repository = repository_dir.join(section.repo_path());
+
+            // A block is compared with the lines between the markers of the local file
+            if let Some(name) = section.block_name() {
+                let path = section.path();
+                let local_block = block::read(&local)?.and_then(|content| block::extract(content.as_str(), name.as_str()));
+                match (local_block, block::read(&repository)?) {
+                    (Some(_), None) => changes.push(Change { path, state: State::LocalOnly }),
+                    (None, Some(_)) => changes.push(Change { path, state: State::RepositoryOnly }),
+                    (Some(local_block), Some(repository_block)) if local_block != repository_block => {
+                        changes.push(Change { path, state: State::Modified });
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            for (source, destination) in plan(local.as_path(), repository.as_path())? {
+                let path = source.strip_prefix(&home_dir)?.to_path_buf();
+                if !destination.exists() {
+                    changes.push(Change { path, state: State::LocalOnly });
+                } else if fs::read(&source)? != local_content(&repository_dir, &destination, section.local_overrides())? {
+                    changes.push(Change { path, state: State::Modified });
+                }
+            }
+
+            for (_, destination) in plan(repository.as_path(), local.as_path())? {
+                if !destination.exists() {
+                    let path = destination.strip_prefix(&home_dir)?.to_path_buf();
+                    changes.push(Change { path, state: State::RepositoryOnly });
+                }
+            }
+        }
+
+        let (upstream, ahead, behind) = match upstream {
+            Some((upstream, ahead, behind)) => (Some(upstream), ahead, behind),
+            None => (None, 0, 0),
+        };
+        self.report.status = Some(Status { branch: branch_name, upstream, ahead, behind, dirty, queued, profile, changes, skipped });
+        Ok(())
+    }
+
+    /// Write to local the version of the path at the revision or date of the working branch,
+    /// the local files are backed up before and the branch is not touched
+    pub fn checkout_file(&mut self, path: &Path, at: &str) -> Result<(), Error> {
+        let home_dir = self.home_dir.clone();
+
+        // Get current branch name
+        let branch_name = self.git()?.get_current_branch_name()?;
+
+        // Check if branch is not a backup
+        validate::branch(branch_name.as_str())?;
+
+        // Check if repository is not dirty
+        validate::repository(self.git()?)?;
+
+        // Only files of the active sections can be restored
+        let path = self.relative_to_home(path)?;
+        if !self.sections()?.iter().any(|section| path.starts_with(section.path())) {
+            return Err(Error::new(Kind::Usage, "the path is not inside any section of the active profile".to_string()).with_path(path.as_path()));
+        }
+
+        // The revision can be a date or anything git understands
+        let oid = match time::parse(at) {
+            Some(date) => match self.git()?.commit_at(branch_name.as_str(), date.timestamp())? {
+                Some(oid) => oid,
+                None => return Err(Error::new(Kind::Usage, format!("there are no commits before {}", at)).with_branch(branch_name.as_str())),
+            },
+            None => self.git()?.resolve(at)?,
+        };
+        let repository_path = self.config()?.to_repository(path.as_path());
+        let repository_files = self.git()?.list_files(oid, repository_path.as_path())?;
+        if repository_files.is_empty() {
+            return Err(Error::new(Kind::Usage, format!("the path does not exist at {}", oid)).with_path(path.as_path()));
+        }
+        let config = self.config()?.clone();
+        let files: Vec<PathBuf> = repository_files.iter().map(|file| config.to_local(file)).collect();
+
+        // Backup local files
+        let old_oid = self.git()?.head_oid()?;
+        let backup_branch_name = self.backup_local_files(branch_name.as_str())?;
 
-        // Push the working branch
-        self.git()?.push(branch_name.as_str())?;
+        // Write the old version of the files with the current local overrides on top
+        let created: Vec<PathBuf> = files.iter().filter(|file| !home_dir.join(file).exists()).cloned().collect();
+        let repository_dir = self.repository_dir.clone();
+        let git = self.git()?;
+        Transaction::new().execute(|transaction| {
+            for (file, repository_file) in files.iter().zip(&repository_files) {
+                if let Some(content) = git.read_file(oid, repository_file.as_path())? {
+                    let content = with_overrides(&repository_dir, &repository_dir.join(repository_file), config.local_overrides(file), content)?;
+                    let content = with_block(home_dir.join(file).as_path(), config.block_name(file), content)?;
+                    transaction.write(home_dir.join(file).as_path(), content.as_slice())?;
+                }
+            }
+            Ok(())
+        })?;
+
+        self.report.copied_to_local.extend(files.iter().cloned());
+        self.report.restored_from = Some(oid.to_string());
+        let update = Update { backup_branch_name, written: files, created };
+        self.record(Operation::CheckoutFile, branch_name.as_str(), old_oid, update, false)
+    }
 
+    /// Report the commits of the working branch, and of its backup branches if backups is true,
+    /// that changed the path and were committed between since and until
+    pub fn log(&mut self, path: Option<&Path>, backups: bool, since: Option<&str>, until: Option<&str>, patch: bool) -> Result<(), Error> {
+        let branch_name = self.git()?.get_current_branch_name()?;
+
+        let mut branch_names = vec![branch_name.clone()];
+        if backups {
+            for name in self.git()?.local_branch_names()? {
+                if is_backup_of(branch_name.as_str(), name.as_str()) {
+                    branch_names.push(name);
+                }
+            }
+        }
+
+        let path = match path {
+            Some(path) => {
+                let path = self.relative_to_home(path)?;
+                Some(self.config()?.to_repository(path.as_path()))
+            }
+            None => None,
+        };
+        let since = match since {
+            Some(since) => Some(parse_date(since)?),
+            None => None,
+        };
+        let until = match until {
+            Some(until) => Some(parse_date(until)?),
+            None => None,
+        };
+
+        let logged = self.git()?.log(&branch_names, path.as_deref(), since, until, patch)?;
+        for commit in logged {
+            self.report.log.push(LogEntry {
+                oid: commit.oid.to_string(),
+                author: commit.author,
+                email: commit.email,
+                host: message::host(commit.message.as_str()),
+                time: time::format_timestamp(commit.time),
+                summary: commit.message.lines().next().unwrap_or_default().to_string(),
+                files: commit.files,
+                patch: commit.patch,
+            });
+        }
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Check the config file and report its problems, fail if there is at least one
+    pub fn config_check(&mut self) -> Result<(), Error> {
+        let config_file = self.config_file();
+        let mut diagnostics = Config::check(&config_file)?;
+        let count = diagnostics.len();
+        self.report.diagnostics.append(&mut diagnostics);
+        match count {
+            0 => Ok(()),
+            _ => Err(Error::new(Kind::Config, format!("the config has {} problem(s), fix them and retry", count)).with_path(&config_file)),
+        }
+    }
+
+    /// Rewrite the config of the working branch in the passed format, check that no value has been lost
+    /// (comments are dropped) and commit the new file in place of the old one
+    pub fn config_convert(&mut self, to: Format) -> Result<(), Error> {
+
+        // Check if repository is not dirty
+        validate::repository(self.git()?)?;
+
+        let from_file = self.config_file();
+        let to_file = self.repository_dir.join(format!("config.{}", to.extension()));
+        if from_file == to_file {
+            return Err(Error::new(Kind::Usage, format!("the config is already in {}", to.extension())).with_path(&from_file));
+        }
+        if to_file.exists() {
+            return Err(Error::new(Kind::Usage, "the file already exists, remove it first".to_string()).with_path(&to_file));
+        }
+
+        // Write the new file and read it back to compare it with the original
+        let config = Config::new(&from_file)?;
+        config.save(&to_file)?;
+        let converted = Config::new(&to_file)?;
+        if serde_json::to_value(&config)? != serde_json::to_value(&converted)? {
+            fs::remove_file(&to_file)?;
+            return Err(Error::new(Kind::Config, format!("the config can not be converted to {} without losing data", to.extension())).with_path(&from_file));
+        }
+
+        // Commit the new file in place of the old one, if that fails the repository is reset
+        // and the new file removed, so the old config is still the one in use
+        let branch_name = self.git()?.get_current_branch_name()?;
+        let head_oid = self.git()?.head_oid()?;
+        let from_path = from_file.strip_prefix(&self.repository_dir)?.to_path_buf();
+        let to_path = to_file.strip_prefix(&self.repository_dir)?.to_path_buf();
+        let message = format!("Convert config to {}", to.extension());
+        let oid = match self.replace_and_commit(&from_path, &to_path, message.as_str()) {
+            Ok(oid) => oid,
+            Err(error) => {
+                let _ = self.git().and_then(|git| git.reset_hard(head_oid));
+                let _ = fs::remove_file(&to_file);
+                self.free_config();
+                return Err(error);
+            }
+        };
+        self.report.commits.push(Commit { branch: branch_name, oid: oid.to_string() });
+
+        // The old file is no more tracked, delete it only now that the new one is committed
+        fs::remove_file(&from_file).map_err(|e| Error::from(e).with_path(&from_file))?;
+        self.free_config();
+        Ok(())
+    }
+
+    /// Stage the removal of a file and the addition of another one and commit them
+    fn replace_and_commit(&mut self, from_path: &Path, to_path: &Path, message: &str) -> Result<Oid, Error> {
+        self.git()?.remove(from_path)?;
+        self.git()?.add(to_path)?;
+        self.commit(message)
+    }
+
+    /// Activate the profile on this machine, only its sections are copied by the next operations,
+    /// with None all the sections are used again
+    pub fn profile_use(&mut self, name: Option<&str>) -> Result<(), Error> {
+        if let Some(name) = name {
+            if !self.config()?.profiles().contains_key(name) {
+                let names: Vec<&str> = self.config()?.profiles().keys().map(|name| name.as_str()).collect();
+                return Err(Error::new(Kind::Usage, format!("the profile '{}' is not in the config, the profiles are: {}", name, names.join(", "))));
+            }
+        }
+        let mut profile = ActiveProfile::load(&self.profile_file)?;
+        profile.set(name);
+        profile.save(&self.profile_file)?;
+        self.report.profile = name.map(|name| name.to_string());
+        Ok(())
+    }
+
+    /// Report the remote branch tracked by each working branch
+    pub fn remote_show(&mut self) -> Result<(), Error> {
+        for branch_name in self.git()?.local_branch_names()? {
+            if is_backup(branch_name.as_str()) {
+                continue;
+            }
+            let upstream = self.git()?.upstream(branch_name.as_str())?;
+            self.report.tracking.push(Tracking { branch: branch_name, upstream });
+        }
+        Ok(())
+    }
+
+    /// Track the upstream, in the form REMOTE or REMOTE/BRANCH, with the passed working branch
+    /// or the current one. If only REMOTE is passed the remote branch with the same name is tracked
+    pub fn remote_set(&mut self, branch_name: Option<&str>, upstream: &str) -> Result<(), Error> {
+        let branch_name = match branch_name {
+            Some(branch_name) => branch_name.to_string(),
+            None => self.git()?.get_current_branch_name()?,
+        };
+
+        // Check if branch is not a backup
+        validate::branch(branch_name.as_str())?;
+
+        let (remote_name, remote_branch_name) = match upstream.find('/') {
+            Some(index) => (&upstream[..index], &upstream[index + 1..]),
+            None => (upstream, branch_name.as_str()),
+        };
+        if remote_branch_name.is_empty() {
+            return Err(Error::new(Kind::Usage, format!("'{}' is not a valid upstream, use REMOTE or REMOTE/BRANCH", upstream)));
+        }
+
+        self.git()?.set_upstream(branch_name.as_str(), remote_name, remote_branch_name)?;
+
+        let upstream = self.git()?.upstream(branch_name.as_str())?;
+        self.report.tracking.push(Tracking { branch: branch_name, upstream });
+        Ok(())
+    }
+
+    /// Revert the last pull or push restoring the local files and resetting the working branch,
+    /// if force_revert is true a revert commit is created and pushed instead of resetting the branch
+    pub fn undo(&mut self, force_revert: bool) -> Result<(), Error> {
+
+        // Get current branch name
+        let branch_name = self.git()?.get_current_branch_name()?;
+
+        // Check if branch is not a backup
+        validate::branch(branch_name.as_str())?;
+
+        // Check if repository is not dirty
+        validate::repository(self.git()?)?;
+
+        let mut journal = Journal::load(&self.journal_file)?;
+        let entry = match journal.last_mut() {
+            Some(entry) => entry.clone(),
+            None => return Err(Error::new(Kind::Other, "there is no pull or push to undo".to_string())),
+        };
+
+        if entry.branch != branch_name {
+            let message = format!("the last {} was executed on another branch, checkout it to undo", entry.operation.as_str());
+            return Err(Error::new(Kind::Other, message).with_branch(entry.branch.as_str()));
+        }
+
+        if self.git()?.head_oid()?.to_string() != entry.new_oid {
+            let message = format!("branch has changed since the last {}, can not undo", entry.operation.as_str());
+            return Err(Error::new(Kind::Conflict, message).with_branch(branch_name.as_str()));
+        }
+
+        // A push that failed or was queued can be undone like a pull, only a push that reached the remote is reverted
+        let pushed = entry.operation == Operation::Push && self.git()?.remote_contains(branch_name.as_str(), Oid::from_str(entry.new_oid.as_str())?)?;
+        if pushed && !force_revert {
+            let message = "the last push already reached the remote, use --force-revert to push a revert commit".to_string();
+            return Err(Error::new(Kind::Conflict, message).with_branch(branch_name.as_str()));
+        }
+        if !pushed && force_revert {
+            let message = format!("--force-revert only reverts a push that reached the remote, undo the last {} without it", entry.operation.as_str());
+            return Err(Error::new(Kind::Usage, message).with_branch(branch_name.as_str()));
+        }
+
+        // Restore local files
+        self.restore_local_files(&entry)?;
+
+        // Move the working branch back
+        let old_oid = Oid::from_str(entry.old_oid.as_str())?;
+        if force_revert {
+            let message = format!("Revert {} of {}", entry.operation.as_str(), entry.time);
+            let message = message::with_host(message.as_str(), host::hostname().as_str());
+            let identity = self.identity()?;
+            let oid = self.git()?.revert_to(old_oid, message.as_str(), &identity)?;
+            self.report.commits.push(Commit { branch: branch_name.clone(), oid: oid.to_string() });
+        } else {
+            self.git()?.reset_hard(old_oid)?;
+        }
+
+        // The branch is no more queued if the remote already has the commit it was moved back to
+        if entry.queued && self.git()?.remote_contains(branch_name.as_str(), old_oid)? {
+            let mut queue = Queue::load(&self.queue_file)?;
+            queue.remove(branch_name.as_str());
+            queue.save(&self.queue_file)?;
+        }
+
+        // The entry is undone once the branch moved, even if the revert commit can not be pushed now
+        if let Some(entry) = journal.last_mut() {
+            entry.undone = true;
+        }
+        journal.save(&self.journal_file)?;
+
+        if !force_revert {
+            return Ok(());
+        }
+
+        // Push the revert commit, if the remote can not be reached it is queued like an offline push
+        let remote_name = self.required_remote_name(branch_name.as_str(), None)?;
+        match self.push_branch(branch_name.as_str(), remote_name.as_str()) {
+            Err(ref error) if error.kind() == Kind::Network => {
+                let mut queue = Queue::load(&self.queue_file)?;
+                queue.add(branch_name.as_str());
+                queue.save(&self.queue_file)?;
+                self.report.queued.push(branch_name);
+                Ok(())
+            }
+            pushed => pushed,
+        }
+    }
+}
+
+/// Parse a date passed as option to seconds since epoch
+fn parse_date(date: &str) -> Result<i64, Error> {
+    match time::parse(date) {
+        Some(date) => Ok(date.timestamp()),
+        None => Err(Error::new(Kind::Usage, format!("invalid date '{}', use YYYY-MM-DD, YYYY-MM-DD HH:MM or RFC 3339", date))),
+    }
+}
+
+/// Content of the local file with the block of the repository file spliced in,
+/// None if the repository file does not exist
+fn spliced(local: &Path, repository: &Path, name: &str) -> Result<Option<String>, Error> {
+    let block = match block::read(repository)? {
+        Some(block) => block,
+        None => return Ok(None),
+    };
+    let content = block::read(local)?.unwrap_or_default();
+    Ok(Some(block::splice(content.as_str(), name, block.as_str())))
+}
+
+/// Splice the block read from the repository in the local file, the content is returned as it is
+/// if the file is copied whole
+fn with_block(local: &Path, name: Option<String>, content: Vec<u8>) -> Result<Vec<u8>, Error> {
+    match name {
+        Some(name) => {
+            let local_content = block::read(local)?.unwrap_or_default();
+            let block = String::from_utf8_lossy(content.as_slice()).to_string();
+            Ok(block::splice(local_content.as_str(), name.as_str(), block.as_str()).into_bytes())
+        }
+        None => Ok(content),
+    }
+}
+
+/// File in .local of the repository dir with the machine-local overrides of a repository file
+fn overrides_file(repository_dir: &Path, repository_file: &Path) -> Result<PathBuf, Error> {
+    Ok(repository_dir.join(".local").join(repository_file.strip_prefix(repository_dir)?))
+}
+
+/// Content of the repository file as written to local disk, with the local overrides on top if enabled
+fn local_content(repository_dir: &Path, repository_file: &Path, overrides: bool) -> Result<Vec<u8>, Error> {
+    let content = fs::read(repository_file).map_err(|e| Error::from(e).with_path(repository_file))?;
+    with_overrides(repository_dir, repository_file, overrides, content)
+}
+
+/// Content of the repository file, read from disk or from a commit, with the local overrides on top if enabled
+fn with_overrides(repository_dir: &Path, repository_file: &Path, overrides: bool, content: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let overrides_file = overrides_file(repository_dir, repository_file)?;
+    if !overrides || !overrides_file.is_file() {
+        return Ok(content);
+    }
+    merge::overlay(repository_file, content.as_slice(), fs::read(&overrides_file)?.as_slice())
+        .map_err(|reason| Error::new(Kind::Config, format!("can not apply the local overrides: {}", reason)).with_path(&overrides_file))
+}
+
+/// Content of the local file as written to the repository, with the overridden keys set back to their shared value
+fn repository_content(repository_dir: &Path, local_file: &Path, repository_file: &Path) -> Result<Vec<u8>, Error> {
+    let content = fs::read(local_file).map_err(|e| Error::from(e).with_path(local_file))?;
+    let overrides_file = overrides_file(repository_dir, repository_file)?;
+    if !overrides_file.is_file() {
+        return Ok(content);
+    }
+    let shared = match repository_file.is_file() {
+        true => fs::read(repository_file)?,
+        false => vec![],
+    };
+    merge::strip(repository_file, content.as_slice(), fs::read(&overrides_file)?.as_slice(), shared.as_slice())
+        .map_err(|reason| Error::new(Kind::Config, format!("can not remove the local overrides: {}", reason)).with_path(&overrides_file))
+}
+
+/// Keep the local overrides out of git, even when all the files are added by hand
+fn exclude_local_overrides(repository_dir: &Path) -> Result<(), Error> {
+    let exclude = repository_dir.join(".git/info/exclude");
+    let content = fs::read_to_string(&exclude).unwrap_or_default();
+    if content.lines().any(|line| line.trim() == "/.local/") {
+        return Ok(());
+    }
+    if let Some(parent) = exclude.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let separator = match content.is_empty() || content.ends_with('\n') {
+        true => "",
+        false => "\n",
+    };
+    fs::write(&exclude, format!("{}{}/.local/\n", content, separator)).map_err(|e| Error::from(e).with_path(&exclude))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Repository;
+    use core::config::Config;
+    use core::config::Section;
+    use core::journal::Journal;
+    use core::journal::Operation;
+    use core::queue::Queue;
+    use git2;
+    use git2::Oid;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use utils::error::Kind;
+    use utils::git::Helper;
+    use utils::git::Identity;
+    use utils::progress::Progress;
+
+    /// Temp dir with a bare remote and the homes of two machines, a and b, sharing the same branch
+    fn machines(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("scog_test_{}_{}", name, ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("a")).unwrap();
+        fs::create_dir_all(dir.join("b")).unwrap();
+        git2::Repository::init_bare(dir.join("remote.git")).unwrap();
+        let remote = dir.join("remote.git").to_string_lossy().to_string();
+
+        fs::write(dir.join("a/.bashrc"), "alias ll='ls -l'\n").unwrap();
+        open(&dir, "a").init(Some(remote.as_str()), &[PathBuf::from(".bashrc")]).unwrap();
+        open(&dir, "b").clone(remote.as_str(), None, true, false).unwrap();
+        dir
+    }
+
+    /// Repository of the machine with its home in the temp dir, a new one for each operation like each run of scog
+    fn open(dir: &Path, machine: &str) -> Repository {
+        let mut repository = Repository::new(Some(dir.join(machine).join(".scog")), Progress::Silent);
+        repository.home_dir = dir.join(machine);
+        repository
+    }
+
+    fn head(dir: &Path, machine: &str) -> Oid {
+        open(dir, machine).git().unwrap().head_oid().unwrap()
+    }
+
+    fn branch(dir: &Path, machine: &str) -> String {
+        open(dir, machine).git().unwrap().get_current_branch_name().unwrap()
+    }
+
+    /// Commit of the branch on the remote, None if the remote does not have the branch
+    fn remote_oid(dir: &Path, branch_name: &str) -> Option<Oid> {
+        let remote = git2::Repository::open_bare(dir.join("remote.git")).unwrap();
+        let reference = remote.find_reference(format!("refs/heads/{}", branch_name).as_str()).ok();
+        reference.and_then(|reference| reference.target())
+    }
+
+    fn bashrc(dir: &Path, machine: &str) -> String {
+        fs::read_to_string(dir.join(machine).join(".bashrc")).unwrap()
+    }
+
+    #[test]
+    fn test_undo_pull() {
+        let dir = machines("undo_pull");
+        fs::write(dir.join("a/.bashrc"), "alias ll='ls -la'\n").unwrap();
+        open(&dir, "a").push(None, false, Some("Show hidden files")).unwrap();
+
+        let old_oid = head(&dir, "b");
+        open(&dir, "b").pull(None).unwrap();
+        assert_eq!(bashrc(&dir, "b"), "alias ll='ls -la'\n");
+        assert_eq!(head(&dir, "b"), head(&dir, "a"));
+
+        // A pull is never reverted on the remote
+        let error = open(&dir, "b").undo(true).err().unwrap();
+        assert_eq!(error.kind(), Kind::Usage);
+
+        let mut repository = open(&dir, "b");
+        repository.undo(false).unwrap();
+        assert_eq!(bashrc(&dir, "b"), "alias ll='ls -l'\n");
+        assert_eq!(head(&dir, "b"), old_oid);
+        let mut journal = Journal::load(&repository.journal_file).unwrap();
+        assert_eq!(journal.last_mut().unwrap().new_oid, old_oid.to_string());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_undo_pushed_push() {
+        let dir = machines("undo_push");
+        let branch_name = branch(&dir, "b");
+        let old_oid = head(&dir, "b");
+        fs::write(dir.join("b/.bashrc"), "alias ll='ls -la'\n").unwrap();
+        open(&dir, "b").push(None, false, Some("Show hidden files")).unwrap();
+        let new_oid = head(&dir, "b");
+        assert_eq!(remote_oid(&dir, branch_name.as_str()), Some(new_oid));
+
+        // The remote already has the push, the branch is not moved back without --force-revert
+        let error = open(&dir, "b").undo(false).err().unwrap();
+        assert_eq!(error.kind(), Kind::Conflict);
+        assert_eq!(head(&dir, "b"), new_oid);
+
+        // The revert commit goes on top of the push and reaches the remote
+        let mut repository = open(&dir, "b");
+        repository.undo(true).unwrap();
+        let revert_oid = head(&dir, "b");
+        assert_eq!(repository.report().commits.len(), 1);
+        assert_eq!(remote_oid(&dir, branch_name.as_str()), Some(revert_oid));
+        let git = git2::Repository::open(dir.join("b/.scog")).unwrap();
+        let revert = git.find_commit(revert_oid).unwrap();
+        assert_eq!(revert.parent_id(0).unwrap(), new_oid);
+        assert_eq!(revert.tree_id(), git.find_commit(old_oid).unwrap().tree_id());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pull_rolled_back_when_copy_fails() {
+        let dir = machines("roll_back");
+
+        // The other machine changes .bashrc and adds a file whose dir is a file on this machine
+        fs::write(dir.join("a/.bashrc"), "alias ll='ls -la'\n").unwrap();
+        fs::create_dir_all(dir.join("a/.config/app")).unwrap();
+        fs::write(dir.join("a/.config/app/settings"), "theme=dark\n").unwrap();
+        let sections = vec![Section::new(".bashrc".to_string()), Section::new(".config/app".to_string())];
+        Config::from_sections(sections).save(&dir.join("a/.scog/config.yaml")).unwrap();
+        let helper = Helper::new(&dir.join("a/.scog"), Progress::Silent).unwrap();
+        helper.add(Path::new("config.yaml")).unwrap();
+        helper.commit("Add the app settings", &Identity { name: None, email: None, signing: None }).unwrap();
+        open(&dir, "a").push(None, false, Some("Show hidden files")).unwrap();
+        fs::create_dir_all(dir.join("b/.config")).unwrap();
+        fs::write(dir.join("b/.config/app"), "not a dir").unwrap();
+
+        let old_oid = head(&dir, "b");
+        let error = open(&dir, "b").pull(None).err().unwrap();
+        assert_eq!(error.kind(), Kind::Io);
+
+        // The copied .bashrc is restored and the branch is back where it was
+        assert_eq!(bashrc(&dir, "b"), "alias ll='ls -l'\n");
+        assert_eq!(fs::read_to_string(dir.join("b/.config/app")).unwrap(), "not a dir");
+        assert_eq!(head(&dir, "b"), old_oid);
+        let mut journal = Journal::load(&open(&dir, "b").journal_file).unwrap();
+        assert_eq!(journal.last_mut().unwrap().new_oid, old_oid.to_string());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_offline_push_then_sync() {
+        let dir = machines("offline_push");
+        let branch_name = branch(&dir, "b");
+        let remote_before = remote_oid(&dir, branch_name.as_str());
+        fs::write(dir.join("b/.bashrc"), "alias ll='ls -la'\n").unwrap();
+
+        let mut repository = open(&dir, "b");
+        repository.push(None, true, Some("Show hidden files")).unwrap();
+        assert_eq!(repository.report().queued, vec![branch_name.clone()]);
+        assert!(Queue::load(&repository.queue_file).unwrap().contains(branch_name.as_str()));
+        assert_eq!(remote_oid(&dir, branch_name.as_str()), remote_before);
+
+        // A remote that can not be reached leaves the branch queued
+        let git = git2::Repository::open(dir.join("b/.scog")).unwrap();
+        git.config().unwrap().set_str("remote.origin.pushurl", "ssh://git@127.0.0.1:1/remote.git").unwrap();
+        let mut repository = open(&dir, "b");
+        repository.sync(None).unwrap();
+        assert_eq!(repository.report().queued, vec![branch_name.clone()]);
+        assert!(Queue::load(&repository.queue_file).unwrap().contains(branch_name.as_str()));
+        assert_eq!(remote_oid(&dir, branch_name.as_str()), remote_before);
+
+        git.config().unwrap().remove("remote.origin.pushurl").unwrap();
+        let mut repository = open(&dir, "b");
+        repository.sync(None).unwrap();
+        assert!(repository.report().queued.is_empty());
+        assert!(!Queue::load(&repository.queue_file).unwrap().contains(branch_name.as_str()));
+        assert_eq!(remote_oid(&dir, branch_name.as_str()), Some(head(&dir, "b")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_checkout_new_branch() {
+        let dir = machines("checkout");
+
+        let mut repository = open(&dir, "b");
+        repository.checkout("work", None, None).unwrap();
+        assert_eq!(repository.report().branch, Some("work".to_string()));
+        assert_eq!(branch(&dir, "b"), "work");
+        assert_eq!(remote_oid(&dir, "work"), Some(head(&dir, "b")));
+        assert_eq!(open(&dir, "b").git().unwrap().tracked_remote("work").unwrap(), Some("origin".to_string()));
+
+        let mut journal = Journal::load(&repository.journal_file).unwrap();
+        let entry = journal.last_mut().unwrap();
+        assert_eq!(entry.operation, Operation::Checkout);
+        assert_eq!(entry.branch, "work");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
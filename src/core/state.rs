@@ -0,0 +1,57 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_yaml;
+use std::fs;
+use std::path::Path;
+use utils::error::Error;
+
+// The journal, the queue and the active profile are yaml files in .git/scog,
+// they describe the state of this machine so they are never synced
+
+/// Read the state from the file, the default state if the file does not exist yet
+pub fn load<T: DeserializeOwned + Default>(file: &Path) -> Result<T, Error> {
+    if !file.is_file() {
+        return Ok(T::default());
+    }
+    let content = fs::read_to_string(file).map_err(|e| Error::from(e).with_path(file))?;
+    serde_yaml::from_str(content.as_str()).map_err(|e| Error::from(e).with_path(file))
+}
+
+/// Write the state to the file, creating its dir if needed
+pub fn save<T: Serialize>(state: &T, file: &Path) -> Result<(), Error> {
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::from(e).with_path(parent))?;
+    }
+    let content = serde_yaml::to_string(state).map_err(|e| Error::from(e).with_path(file))?;
+    fs::write(file, content).map_err(|e| Error::from(e).with_path(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::load;
+    use super::save;
+    use std::collections::BTreeMap;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_load_and_save() {
+        let dir = env::temp_dir().join(format!("scog_test_state_{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let file = dir.join("scog/state.yaml");
+
+        let state: BTreeMap<String, String> = load(&file).unwrap();
+        assert!(state.is_empty());
+
+        let mut state = BTreeMap::new();
+        state.insert("name".to_string(), "laptop".to_string());
+        save(&state, &file).unwrap();
+        assert_eq!(load::<BTreeMap<String, String>>(&file).unwrap(), state);
+
+        // Errors tell which file can not be read
+        fs::write(&file, "- not a map").unwrap();
+        let error = load::<BTreeMap<String, String>>(&file).err().unwrap();
+        assert_eq!(error.path(), Some(file.as_path()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,17 +1,27 @@
 use utils::error::Error;
+use utils::error::Kind;
 use utils::backup::is_backup;
 use utils::git::Helper;
 
 pub fn branch(branch_name: &str) -> Result<(), Error> {
     match is_backup(branch_name) {
-        true => Err(format!("can not pull or push from backup branch: '{}'.", branch_name))?,
+        true => Err(Error::new(Kind::BackupBranch, "can not pull or push from backup branch".to_string()).with_branch(branch_name)),
         false => Ok(()),
     }
 }
 
+/// Check that the name can be used for a new working branch,
+/// names starting with an underscore are reserved for backup branches
+pub fn branch_name(branch_name: &str) -> Result<(), Error> {
+    if branch_name.is_empty() || branch_name.starts_with('_') {
+        return Err(Error::new(Kind::Usage, "the branch name can not be empty or start with an underscore".to_string()).with_branch(branch_name));
+    }
+    Ok(())
+}
+
 pub fn repository(git: &Helper) -> Result<(), Error> {
     match git.is_dirty()? {
-        true => Err("can not pull or push if repository is dirty".to_string())?,
+        true => Err(Error::new(Kind::DirtyRepository, "can not pull or push if repository is dirty".to_string())),
         false => Ok(()),
     }
 }
@@ -19,10 +29,18 @@ pub fn repository(git: &Helper) -> Result<(), Error> {
 #[cfg(test)]
 mod tests {
     use super::branch;
+    use super::branch_name;
 
     #[test]
     fn test_validate_branch() {
         assert!(branch("_backup_test_bla").is_err());
         assert!(branch("master").is_ok());
     }
+
+    #[test]
+    fn test_validate_branch_name() {
+        assert!(branch_name("_laptop").is_err());
+        assert!(branch_name("").is_err());
+        assert!(branch_name("laptop").is_ok());
+    }
 }
\ No newline at end of file
@@ -1,49 +1,71 @@
 extern crate chrono;
+#[macro_use]
+extern crate clap;
 extern crate git2;
+extern crate hostname;
 extern crate regex;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 extern crate serde_yaml;
+extern crate toml;
 
-use commands::help;
-use core::context::Context;
+use clap::ErrorKind as ClapErrorKind;
+use core::report::Report;
 use std::env;
 use std::process;
 use utils::error::Error;
+use utils::error::Kind;
+use utils::output;
+use utils::output::Output;
+use utils::output::Verbosity;
 
 mod commands;
 mod core;
 mod utils;
 
 fn main() {
-    match exec() {
+    let matches = match commands::app().get_matches_safe() {
+        Ok(matches) => matches,
+        Err(e) => {
+            match e.kind {
+                ClapErrorKind::HelpDisplayed | ClapErrorKind::VersionDisplayed => {
+                    println!("{}", e.message);
+                    process::exit(0);
+                }
+                ClapErrorKind::MissingArgumentOrSubcommand => {
+                    eprintln!("{}", e.message);
+                    process::exit(Kind::Usage.exit_code());
+                }
+                _ => {
+                    // Options are not parsed, so search the output format in the raw args
+                    let args: Vec<String> = env::args().collect();
+                    let json = args.windows(2).any(|args| args[0] == "--output" && args[1] == "json")
+                        || args.iter().any(|arg| arg == "--output=json");
+                    let format = if json { Output::Json } else { Output::Human };
+
+                    let message = e.message.trim_start_matches("error: ").to_string();
+                    let result = Err(Error::new(Kind::Usage, message));
+                    output::print(format, Verbosity::Normal, "", &Report::default(), &result);
+                    process::exit(Kind::Usage.exit_code());
+                }
+            }
+        }
+    };
+
+    let mut context = commands::context(&matches);
+    let result = commands::exec(&mut context, &matches);
+
+    let command = matches.subcommand_name().unwrap_or_default();
+    output::print(context.output(), context.verbosity(), command, &context.report(), &result);
+
+    match result {
         Ok(_) => {
             process::exit(0);
         }
         Err(e) => {
-            println!("scog: {}", e.error());
-            process::exit(1);
+            process::exit(e.kind().exit_code());
         }
     }
 }
-
-fn exec() -> Result<(), Error> {
-    let mut context = Context::new();
-    let mut args: Vec<String> = env::args().collect();
-    // First args is the name of the program
-    args.remove(0);
-    if args.len() > 0 {
-        match args.remove(0).as_str() {
-            "--help" => help::exec(&mut context, &mut args),
-            cmd => {
-                match commands::exec(cmd) {
-                    Some(f) => f(&mut context, &mut args),
-                    None => Err(format!("'{}' is not a valid COMMAND.", cmd))?,
-                }
-            }
-        }
-    } else {
-        Err(format!("no COMMAND defined."))?
-    }
-}
\ No newline at end of file
@@ -1,3 +1,4 @@
+use regex;
 use regex::Regex;
 use utils::time;
 
@@ -5,6 +6,12 @@ pub fn is_backup(branch_name: &str) -> bool {
     Regex::new(r"^_backup_").unwrap().is_match(branch_name)
 }
 
+/// Check if the backup branch has been created from the passed branch
+pub fn is_backup_of(branch_name: &str, backup_branch_name: &str) -> bool {
+    let pattern = format!(r"^_backup_{}_\d{{4}}-\d{{2}}-\d{{2}}_\d{{2}}-\d{{2}}-\d{{2}}_\d+$", regex::escape(branch_name));
+    Regex::new(pattern.as_str()).unwrap().is_match(backup_branch_name)
+}
+
 pub fn backup_branch_name(from_branch_name: &str) -> String {
     format!("_backup_{}_{}", from_branch_name, time::now_to_string())
 }
@@ -13,6 +20,7 @@ pub fn backup_branch_name(from_branch_name: &str) -> String {
 mod tests {
     use super::backup_branch_name;
     use super::is_backup;
+    use super::is_backup_of;
     use regex::Regex;
 
     #[test]
@@ -27,4 +35,11 @@ mod tests {
         assert!(!is_backup("master"));
         assert!(is_backup("_backup_test"));
     }
+
+    #[test]
+    fn test_is_backup_of() {
+        assert!(is_backup_of("laptop", backup_branch_name("laptop").as_str()));
+        assert!(!is_backup_of("laptop", backup_branch_name("laptop_old").as_str()));
+        assert!(!is_backup_of("laptop", "laptop"));
+    }
 }
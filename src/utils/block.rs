@@ -0,0 +1,89 @@
+use std::fs;
+use std::path::Path;
+use utils::error::Error;
+use utils::error::Kind;
+
+/// Lines that start and end the block owned by scog
+fn markers(name: &str) -> (String, String) {
+    (format!("# >>> scog:{} >>>", name), format!("# <<< scog:{} <<<", name))
+}
+
+/// Index of the start and end marker lines
+fn find(lines: &[&str], name: &str) -> Option<(usize, usize)> {
+    let (start, end) = markers(name);
+    let start = lines.iter().position(|line| line.trim() == start)?;
+    let end = lines[start..].iter().position(|line| line.trim() == end)?;
+    Some((start, start + end))
+}
+
+/// Content between the markers, None if they are missing
+pub fn extract(content: &str, name: &str) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let (start, end) = find(&lines, name)?;
+    Some(lines[start + 1..end].iter().map(|line| format!("{}\n", line)).collect())
+}
+
+/// Replace the content between the markers with the block, the markers are added at the end if missing
+pub fn splice(content: &str, name: &str, block: &str) -> String {
+    let mut block = block.to_string();
+    if !block.is_empty() && !block.ends_with('\n') {
+        block.push('\n');
+    }
+
+    let lines: Vec<&str> = content.lines().collect();
+    match find(&lines, name) {
+        Some((start, end)) => {
+            let mut spliced: String = lines[..=start].iter().map(|line| format!("{}\n", line)).collect();
+            spliced.push_str(block.as_str());
+            spliced.push_str(lines[end]);
+            for line in &lines[end + 1..] {
+                spliced.push('\n');
+                spliced.push_str(line);
+            }
+            if content.ends_with('\n') {
+                spliced.push('\n');
+            }
+            spliced
+        }
+        None => {
+            let (start, end) = markers(name);
+            let mut spliced = content.to_string();
+            if !spliced.is_empty() && !spliced.ends_with('\n') {
+                spliced.push('\n');
+            }
+            format!("{}{}\n{}{}\n", spliced, start, block, end)
+        }
+    }
+}
+
+/// Read the file for a block section, None if it does not exist
+pub fn read(path: &Path) -> Result<Option<String>, Error> {
+    if path.is_dir() {
+        return Err(Error::new(Kind::Config, "a section with mode block must be a file".to_string()).with_path(path));
+    }
+    if !path.exists() {
+        return Ok(None);
+    }
+    fs::read_to_string(path).map(Some).map_err(|e| Error::from(e).with_path(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract;
+    use super::splice;
+
+    #[test]
+    fn test_extract_and_splice() {
+        let content = "export PATH=/usr/bin\n# >>> scog:aliases >>>\nalias ll='ls -l'\n# <<< scog:aliases <<<\nsource /etc/bashrc\n";
+        assert_eq!(extract(content, "aliases"), Some("alias ll='ls -l'\n".to_string()));
+        assert_eq!(extract(content, "other"), None);
+
+        let spliced = splice(content, "aliases", "alias la='ls -a'");
+        assert_eq!(spliced, "export PATH=/usr/bin\n# >>> scog:aliases >>>\nalias la='ls -a'\n# <<< scog:aliases <<<\nsource /etc/bashrc\n");
+
+        let added = splice("export PATH=/usr/bin", "aliases", "alias ll='ls -l'\n");
+        assert_eq!(added, "export PATH=/usr/bin\n# >>> scog:aliases >>>\nalias ll='ls -l'\n# <<< scog:aliases <<<\n");
+        assert_eq!(extract(added.as_str(), "aliases"), Some("alias ll='ls -l'\n".to_string()));
+        assert_eq!(splice("", "aliases", ""), "# >>> scog:aliases >>>\n# <<< scog:aliases <<<\n");
+    }
+}
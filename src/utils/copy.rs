@@ -1,54 +1,70 @@
 use std::fs;
 use utils::error::Error;
+use utils::error::Kind;
 use std::path::Path;
 use std::path::PathBuf;
 
+/// A single file copy, from source to destination
+pub type Operation = (PathBuf, PathBuf);
+
 pub fn copy(source: &Path, destination: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut copied: Vec<PathBuf> = vec![];
+    for (source, destination) in plan(source, destination)? {
+        copy_file(source.as_path(), destination.as_path())?;
+        copied.push(destination);
+    }
+    Ok(copied)
+}
+
+/// Collect all the file copies needed to copy source to destination without writing anything,
+/// so that errors like a file that should replace a dir are detected upfront
+pub fn plan(source: &Path, destination: &Path) -> Result<Vec<Operation>, Error> {
     if source.exists() {
         if source.is_file() {
-            copy_file(source, destination)
+            plan_file(source, destination)
         } else if source.is_dir() {
-            copy_dir(source, destination)
+            plan_dir(source, destination)
         } else {
-            Err(format!("source '{}' is neither a file nor dir", source.to_string_lossy()))?
+            Err(Error::new(Kind::Io, "source is neither a file nor dir".to_string()).with_path(source))
         }
     } else {
         Ok(vec![])
     }
 }
 
-fn copy_dir(source: &Path, destination: &Path) -> Result<Vec<PathBuf>, Error> {
-    let mut copied: Vec<PathBuf> = vec![];
-    for _source in fs::read_dir(&source)? {
+fn plan_dir(source: &Path, destination: &Path) -> Result<Vec<Operation>, Error> {
+    let mut planned: Vec<Operation> = vec![];
+    for _source in fs::read_dir(source)? {
         let _source = _source?.path();
-        let _destination = destination.join(_source.strip_prefix(&source)?);
+        let _destination = destination.join(_source.strip_prefix(source)?);
 
-        // Recall copy to handle dir or file
-        let mut _copied = copy(_source.as_path(), _destination.as_path())?;
+        // Recall plan to handle dir or file
+        let mut _planned = plan(_source.as_path(), _destination.as_path())?;
 
-        copied.append(&mut _copied);
+        planned.append(&mut _planned);
     }
-    Ok(copied)
+    Ok(planned)
 }
 
-fn copy_file(source: &Path, destination: &Path) -> Result<Vec<PathBuf>, Error> {
+fn plan_file(source: &Path, destination: &Path) -> Result<Vec<Operation>, Error> {
     if destination.is_dir() {
-        Err(format!("source '{}' is a file but destination '{}' is a dir", source.to_string_lossy(), destination.to_string_lossy()))?
+        Err(Error::new(Kind::Io, format!("source '{}' is a file but destination is a dir", source.to_string_lossy())).with_path(destination))
     } else {
-        // Create the destination dir if it does not exists
-        match destination.parent() {
-            Some(parent) => {
-                if !parent.exists() {
-                    fs::create_dir_all(parent)?;
-                }
-            }
-            None => {}
+        Ok(vec![(source.to_path_buf(), destination.to_path_buf())])
+    }
+}
+
+/// Copy a single file creating the destination dir if needed
+pub fn copy_file(source: &Path, destination: &Path) -> Result<(), Error> {
+    // Create the destination dir if it does not exists
+    if let Some(parent) = destination.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).map_err(|e| Error::from(e).with_path(parent))?;
         }
+    }
 
-        // Copy the file from source to destination
-        fs::copy(&source, &destination).unwrap();
+    // Copy the file from source to destination
+    fs::copy(source, destination).map_err(|e| Error::from(e).with_path(destination))?;
 
-        // Call each for each copied files
-        Ok(vec![destination.to_path_buf()])
-    }
+    Ok(())
 }
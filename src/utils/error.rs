@@ -1,54 +1,267 @@
 use std;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
 use git2;
+use git2::ErrorClass;
+use git2::ErrorCode;
+use serde_json;
 use serde_yaml;
+use toml;
+
+/// Kind of failure, each kind exits the process with a different code
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kind {
+    /// Generic failure, exit code 1
+    Other,
+    /// Invalid command or arguments, exit code 2
+    Usage,
+    /// Missing or invalid config file, exit code 3
+    Config,
+    /// Authentication with the remote failed, exit code 4
+    Auth,
+    /// The remote can not be reached, exit code 5
+    Network,
+    /// The branch can not be fast forwarded or merged, exit code 6
+    Conflict,
+    /// The repository has uncommitted changes, exit code 7
+    DirtyRepository,
+    /// The operation is not allowed on a backup branch, exit code 8
+    BackupBranch,
+    /// Reading or writing a file failed, exit code 9
+    Io,
+    /// Any other git failure, exit code 10
+    Git,
+}
+
+impl Kind {
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            Kind::Other => 1,
+            Kind::Usage => 2,
+            Kind::Config => 3,
+            Kind::Auth => 4,
+            Kind::Network => 5,
+            Kind::Conflict => 6,
+            Kind::DirtyRepository => 7,
+            Kind::BackupBranch => 8,
+            Kind::Io => 9,
+            Kind::Git => 10,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Kind::Other => "other",
+            Kind::Usage => "usage",
+            Kind::Config => "config",
+            Kind::Auth => "auth",
+            Kind::Network => "network",
+            Kind::Conflict => "conflict",
+            Kind::DirtyRepository => "dirty-repository",
+            Kind::BackupBranch => "backup-branch",
+            Kind::Io => "io",
+            Kind::Git => "git",
+        }
+    }
+}
 
 pub struct Error {
-    error: String,
+    kind: Kind,
+    message: String,
+    path: Option<PathBuf>,
+    branch: Option<String>,
+    source: Option<Box<dyn std::error::Error>>,
 }
 
 impl Error {
-    fn new(error: String) -> Error {
-        Error { error }
+    pub fn new(kind: Kind, message: String) -> Error {
+        Error {
+            kind,
+            message,
+            path: None,
+            branch: None,
+            source: None,
+        }
+    }
+
+    /// Attach the file or dir the error is about
+    pub fn with_path(mut self, path: &Path) -> Error {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Attach the branch the error is about
+    pub fn with_branch(mut self, branch_name: &str) -> Error {
+        self.branch = Some(branch_name.to_string());
+        self
+    }
+
+    /// Attach the error that caused this one
+    pub fn with_source<E: std::error::Error + 'static>(mut self, source: E) -> Error {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        self.message.as_str()
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
     }
 
-    pub fn error(&self) -> &str {
-        self.error.as_str()
+    /// Return the messages of all the errors that caused this one, from the nearest
+    pub fn causes(&self) -> Vec<String> {
+        let mut causes = vec![];
+        let mut source = std::error::Error::source(self);
+        while let Some(error) = source {
+            causes.push(error.to_string());
+            source = error.source();
+        }
+        causes
     }
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(ref path) = self.path {
+            write!(f, " (path: '{}')", path.to_string_lossy())?;
+        }
+        if let Some(ref branch) = self.branch {
+            write!(f, " (branch: '{}')", branch)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.kind.as_str(), self)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_ref().map(|source| source.as_ref())
+    }
+}
 
 impl From<std::io::Error> for Error {
     fn from(error: std::io::Error) -> Self {
-        Error::new(format!("std::io::Error: {}", (&error as &std::error::Error).description().to_string()))
+        Error::new(Kind::Io, "reading or writing a file failed".to_string()).with_source(error)
     }
 }
 
 impl From<serde_yaml::Error> for Error {
     fn from(error: serde_yaml::Error) -> Self {
-        Error::new(format!("serde_yaml::Error: {}", (&error as &std::error::Error).description().to_string()))
+        Error::new(Kind::Config, format!("invalid yaml: {}", error)).with_source(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::new(Kind::Config, format!("invalid json: {}", error)).with_source(error)
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(error: toml::ser::Error) -> Self {
+        Error::new(Kind::Config, format!("can not write toml: {}", error)).with_source(error)
     }
 }
 
 impl From<std::str::Utf8Error> for Error {
     fn from(error: std::str::Utf8Error) -> Self {
-        Error::new(format!("std::str::Utf8Error: {}", (&error as &std::error::Error).description().to_string()))
+        Error::new(Kind::Config, format!("invalid utf-8: {}", error)).with_source(error)
     }
 }
 
 impl From<std::path::StripPrefixError> for Error {
     fn from(error: std::path::StripPrefixError) -> Self {
-        Error::new(format!("std::path::StripPrefixError: {}", (&error as &std::error::Error).description().to_string()))
+        Error::new(Kind::Io, "the path is outside the expected dir".to_string()).with_source(error)
+    }
+}
+
+/// Map the git2 error code and class to the matching kind
+fn git2_kind(code: ErrorCode, class: ErrorClass, message: &str) -> Kind {
+    match (code, class) {
+        (ErrorCode::Auth, _) | (ErrorCode::Certificate, _) => Kind::Auth,
+        (_, ErrorClass::Ssh) if message.contains("authenticat") => Kind::Auth,
+        (_, ErrorClass::Net) | (_, ErrorClass::Ssh) | (_, ErrorClass::Ssl) => Kind::Network,
+        // The socket errors of the transports have the os class
+        (_, ErrorClass::Os) if message.starts_with("failed to connect") || message.starts_with("failed to resolve") => Kind::Network,
+        (ErrorCode::NotFastForward, _) | (ErrorCode::Conflict, _) | (ErrorCode::Unmerged, _) => Kind::Conflict,
+        (ErrorCode::Uncommitted, _) => Kind::DirtyRepository,
+        (_, ErrorClass::Os) | (_, ErrorClass::Filesystem) => Kind::Io,
+        _ => Kind::Git,
     }
 }
 
 impl From<git2::Error> for Error {
     fn from(error: git2::Error) -> Self {
-        Error::new(format!("git2::Error: {}", (&error as &std::error::Error).description().to_string()))
+        let kind = git2_kind(error.code(), error.class(), error.message());
+        let message = match kind {
+            Kind::Auth => "authentication with the remote failed",
+            Kind::Network => "the remote can not be reached",
+            Kind::Io => "reading or writing a file failed",
+            _ => "git operation failed",
+        };
+        Error::new(kind, message.to_string()).with_source(error)
     }
 }
 
 impl From<String> for Error {
     fn from(error: String) -> Self {
-        Error::new(error)
+        Error::new(Kind::Other, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::git2_kind;
+    use super::Error;
+    use super::Kind;
+    use git2::ErrorClass;
+    use git2::ErrorCode;
+    use std::io;
+    use std::path::Path;
+
+    #[test]
+    fn test_git2_kind() {
+        assert_eq!(git2_kind(ErrorCode::Auth, ErrorClass::Net, "unauthorized"), Kind::Auth);
+        assert_eq!(git2_kind(ErrorCode::GenericError, ErrorClass::Ssh, "Failed to authenticate SSH session"), Kind::Auth);
+        assert_eq!(git2_kind(ErrorCode::GenericError, ErrorClass::Net, "unreachable"), Kind::Network);
+        assert_eq!(git2_kind(ErrorCode::GenericError, ErrorClass::Os, "failed to connect to 127.0.0.1: Connection refused"), Kind::Network);
+        assert_eq!(git2_kind(ErrorCode::GenericError, ErrorClass::Os, "could not open 'config'"), Kind::Io);
+        assert_eq!(git2_kind(ErrorCode::NotFastForward, ErrorClass::Reference, "not ff"), Kind::Conflict);
+        assert_eq!(git2_kind(ErrorCode::NotFound, ErrorClass::Reference, "not found"), Kind::Git);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_display_and_causes() {
+        let io = io::Error::new(io::ErrorKind::NotFound, "not found");
+        let error = Error::new(Kind::Io, "can not copy".to_string())
+            .with_source(io)
+            .with_path(Path::new(".bashrc"))
+            .with_branch("master");
+
+        assert_eq!(error.to_string(), "can not copy (path: '.bashrc') (branch: 'master')");
+        assert_eq!(error.causes(), vec!["not found"]);
+        assert_eq!(error.kind().exit_code(), 9);
+
+        // The converted error does not repeat the text of its source
+        let error = Error::from(io::Error::new(io::ErrorKind::NotFound, "not found"));
+        assert_eq!(error.to_string(), "reading or writing a file failed");
+        assert_eq!(error.causes(), vec!["not found"]);
+    }
+}
@@ -0,0 +1,107 @@
+use std::env;
+use std::path::Path;
+
+/// XDG base directories and their default relative to the home dir
+const XDG_DEFAULTS: [(&str, &str); 4] = [
+    ("XDG_CONFIG_HOME", ".config"),
+    ("XDG_DATA_HOME", ".local/share"),
+    ("XDG_STATE_HOME", ".local/state"),
+    ("XDG_CACHE_HOME", ".cache"),
+];
+
+/// Expand a leading ~, $VAR, ${VAR} and ${VAR:-default} with the environment, the XDG base directories
+/// default to their standard location in the home dir. Return the expanded path and the unset variables,
+/// which are left as they are
+pub fn expand(path: &str, home: &Path) -> (String, Vec<String>) {
+    expand_with(path, home, |name| env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+fn expand_with<F: Fn(&str) -> Option<String>>(path: &str, home: &Path, lookup: F) -> (String, Vec<String>) {
+    let home = home.to_string_lossy().to_string();
+    let value = |name: &str| -> Option<String> {
+        match lookup(name) {
+            Some(value) => Some(value),
+            None if name == "HOME" => Some(home.clone()),
+            None => XDG_DEFAULTS.iter()
+                .find(|(variable, _)| *variable == name)
+                .map(|(_, default)| format!("{}/{}", home, default)),
+        }
+    };
+
+    let mut expanded = String::new();
+    let mut unset: Vec<String> = vec![];
+    let mut rest = path;
+    if rest == "~" || rest.starts_with("~/") {
+        expanded.push_str(home.as_str());
+        rest = &rest[1..];
+    }
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        // ${VAR} and ${VAR:-default}
+        if after.starts_with('{') {
+            if let Some(end) = after.find('}') {
+                let inner = &after[1..end];
+                let (name, default) = match inner.find(":-") {
+                    Some(index) => (&inner[..index], Some(&inner[index + 2..])),
+                    None => (inner, None),
+                };
+                match (value(name), default) {
+                    (Some(value), _) => expanded.push_str(value.as_str()),
+                    (None, Some(default)) => expanded.push_str(default),
+                    (None, None) => {
+                        expanded.push_str(&rest[start..start + end + 2]);
+                        unset.push(name.to_string());
+                    }
+                }
+                rest = &after[end + 1..];
+                continue;
+            }
+        }
+
+        // $VAR
+        let length = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+        if length == 0 {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        let name = &after[..length];
+        match value(name) {
+            Some(value) => expanded.push_str(value.as_str()),
+            None => {
+                expanded.push_str(&rest[start..start + length + 1]);
+                unset.push(name.to_string());
+            }
+        }
+        rest = &after[length..];
+    }
+    expanded.push_str(rest);
+    (expanded, unset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_with;
+    use std::path::Path;
+
+    #[test]
+    fn test_expand() {
+        let home = Path::new("/home/davide");
+        let lookup = |name: &str| match name {
+            "XDG_DATA_HOME" => Some("/data".to_string()),
+            "EDITOR" => Some("nvim".to_string()),
+            _ => None,
+        };
+        let expand = |path: &str| expand_with(path, home, lookup);
+
+        assert_eq!(expand("~/.bashrc"), ("/home/davide/.bashrc".to_string(), vec![]));
+        assert_eq!(expand("$XDG_CONFIG_HOME/$EDITOR"), ("/home/davide/.config/nvim".to_string(), vec![]));
+        assert_eq!(expand("${XDG_DATA_HOME}/fonts"), ("/data/fonts".to_string(), vec![]));
+        assert_eq!(expand("${TERMINAL:-alacritty}/config"), ("alacritty/config".to_string(), vec![]));
+        assert_eq!(expand(".config/$TERMINAL/a$"), (".config/$TERMINAL/a$".to_string(), vec!["TERMINAL".to_string()]));
+        assert_eq!(expand("a~/${UNSET}"), ("a~/${UNSET}".to_string(), vec!["UNSET".to_string()]));
+    }
+}
@@ -3,6 +3,7 @@ use git2::Cred;
 use git2::FetchOptions;
 use git2::RemoteCallbacks;
 use utils::error::Error;
+use utils::error::Kind;
 use git2::Repository;
 use git2::Branch;
 use git2::BranchType;
@@ -13,29 +14,87 @@ use git2;
 use std::path::Path;
 use git2::Oid;
 use git2::PushOptions;
+use git2::ResetType;
+use git2::Delta;
+use git2::DiffFormat;
+use git2::DiffOptions;
+use git2::Sort;
+use git2::ObjectType;
+use git2::TreeWalkMode;
+use git2::TreeWalkResult;
+use git2::Signature;
+use git2::Tree;
+use git2::Index;
+use git2::IndexConflict;
+use utils::host;
+use utils::merge;
+use utils::sign;
+use utils::sign::Format;
+use std::path::PathBuf;
+use std::cell::RefCell;
+use std::rc::Rc;
+use utils::progress::Progress;
+use utils::progress::Reporter;
+
+/// Bits of the index entry flags holding the conflict stage
+const INDEX_STAGE_MASK: u16 = 0x3000;
+
+/// Author of the commits and key to sign them, missing values are taken from the git config
+#[derive(Default)]
+pub struct Identity {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub signing: Option<(Format, String)>,
+}
+
+/// A commit returned by log
+pub struct Logged {
+    pub oid: Oid,
+    pub author: String,
+    pub email: String,
+    /// Seconds since epoch
+    pub time: i64,
+    pub message: String,
+    /// Files changed compared to the first parent
+    pub files: Vec<PathBuf>,
+    pub patch: Option<String>,
+}
 
 pub struct Helper {
     repository: Repository,
+    progress: Progress,
 }
 
 impl Helper {
-    pub fn new(work_dir: &Path) -> Result<Helper, Error> {
-        let repository = match Repository::open(work_dir) {
-            Ok(repository) => repository,
-            Err(e) => return Err(e.into()),
-        };
+    pub fn new(work_dir: &Path, progress: Progress) -> Result<Helper, Error> {
+        let repository = Repository::open(work_dir)?;
         let helper = Helper {
             repository,
+            progress,
         };
         Ok(helper)
     }
 
+    /// Create a new empty repository
+    pub fn init(work_dir: &Path, progress: Progress) -> Result<Helper, Error> {
+        let repository = Repository::init(work_dir)?;
+        Ok(Helper { repository, progress })
+    }
+
     /// Retrieve the Branch object of the passed branch
     /// Try to search it locally otherwise try to search it on each remote and return it
     /// on the first matched remote
-    pub fn find_branch(&self, branch_name: &str) -> Result<Branch, Error> {
+    pub fn find_branch(&self, branch_name: &str) -> Result<Branch<'_>, Error> {
+        match self.search_branch(branch_name)? {
+            Some(branch) => Ok(branch),
+            None => Err(Error::new(Kind::Git, "can not locate remote or local branch".to_string()).with_branch(branch_name)),
+        }
+    }
+
+    /// Like find_branch but return None if the branch does not exist
+    fn search_branch(&self, branch_name: &str) -> Result<Option<Branch<'_>>, Error> {
         match self.find_local_branch(branch_name) {
-            Ok(branch) => Ok(branch),
+            Ok(branch) => Ok(Some(branch)),
             Err(error) => {
                 match error.code() {
                     ErrorCode::NotFound => {
@@ -47,30 +106,25 @@ impl Helper {
         }
     }
 
-    fn find_local_branch(&self, branch_name: &str) -> Result<Branch, git2::Error> {
+    fn find_local_branch(&self, branch_name: &str) -> Result<Branch<'_>, git2::Error> {
         self.repository.find_branch(branch_name, BranchType::Local)
     }
 
-    fn find_remote_branch(&self, branch_name: &str) -> Result<Branch, Error> {
+    fn find_remote_branch(&self, branch_name: &str) -> Result<Option<Branch<'_>>, Error> {
         let remotes = self.repository.remotes()?;
-        for remote in remotes.iter() {
-            match remote {
-                Some(remote) => {
-                    let branch = self.find_local_branch(format!("{}/{}", remote, branch_name).as_str());
-                    match branch {
-                        Ok(branch) => return Ok(branch),
-                        Err(error) => {
-                            match error.code() {
-                                ErrorCode::NotFound => continue,
-                                _ => return Err(error.into()),
-                            }
-                        },
+        for remote in remotes.iter().flatten() {
+            let branch = self.repository.find_branch(format!("{}/{}", remote, branch_name).as_str(), BranchType::Remote);
+            match branch {
+                Ok(branch) => return Ok(Some(branch)),
+                Err(error) => {
+                    match error.code() {
+                        ErrorCode::NotFound => continue,
+                        _ => return Err(error.into()),
                     }
                 },
-                None => {},
             }
         }
-        Err(format!("can not locate remote or local branch '{}'", branch_name))?
+        Ok(None)
     }
 
     /// Checkout a local or remote branch, this will behave like git checkout
@@ -79,22 +133,62 @@ impl Helper {
 
         let commit = branch.get().peel_to_commit()?;
 
-        // If the branch is remote, create a new local branch with the same name
-        if branch.get().is_remote() {
-            self.repository.branch(branch_name, &commit, false)?;
-        }
+        // If the branch is remote, create a new local branch with the same name that tracks it
+        let reference = match branch.get().is_remote() {
+            true => {
+                let mut local = self.repository.branch(branch_name, &commit, false)?;
+                local.set_upstream(branch.name()?)?;
+                local.get().name().unwrap_or_default().to_string()
+            }
+            false => {
+                self.repair_upstream(branch_name)?;
+                branch.get().name().unwrap_or_default().to_string()
+            }
+        };
 
         // Checkout files (this will not move HEAD)
         self.checkout_commit(&commit)?;
 
-        // Set the HEAD on the passed branch
-        self.repository.set_head(branch.get().name().unwrap_or_default())?;
+        // Set the HEAD on the local branch
+        self.repository.set_head(reference.as_str())?;
+
+        Ok(())
+    }
+
+    /// Check if a local or remote branch with the passed name exists
+    pub fn branch_exists(&self, branch_name: &str) -> Result<bool, Error> {
+        Ok(self.search_branch(branch_name)?.is_some())
+    }
 
+    /// Create a new local branch from the passed local or remote base branch and checkout it
+    pub fn create_branch(&self, branch_name: &str, base_name: &str) -> Result<(), Error> {
+        let base = self.find_branch(base_name)?;
+        let commit = base.get().peel_to_commit()?;
+        let branch = self.repository.branch(branch_name, &commit, false)?;
+        self.checkout_commit(&commit)?;
+        self.repository.set_head(branch.get().name().unwrap_or_default())?;
         Ok(())
     }
 
+    /// Return the remote tracked by the passed local branch, None if it does not track any remote
+    pub fn tracked_remote(&self, branch_name: &str) -> Result<Option<String>, Error> {
+        let config = self.repository.config()?;
+        Ok(config.get_string(format!("branch.{}.remote", branch_name).as_str()).ok())
+    }
+
+    /// Return the remote tracked by the passed local branch or the first remote if the branch
+    /// does not track any remote, None if there are no remotes
+    pub fn default_remote(&self, branch_name: &str) -> Result<Option<String>, Error> {
+        if let Some(remote) = self.tracked_remote(branch_name)? {
+            return Ok(Some(remote));
+        }
+        let remotes = self.repository.remotes()?;
+        let remote = remotes.iter().flatten().next().map(|remote| remote.to_string());
+        Ok(remote)
+    }
+
     /// Create a new branch and switch to it
-    pub fn branch(&self, branch_name: &str) -> Result<Branch, Error> {
+    pub fn branch(&self, branch_name: &str) -> Result<Branch<'_>, Error> {
         let commit = self.repository.head()?.peel_to_commit()?;
         let branch = self.repository.branch(branch_name, &commit, false)?;
         self.repository.set_head(branch.get().name().unwrap_or_default())?;
@@ -105,51 +199,59 @@ impl Helper {
         self.repository.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
     }
 
-    pub fn remote_callbacks<'a>() -> RemoteCallbacks<'a> {
+    pub fn remote_callbacks<'a>(progress: Progress, operation: &'static str) -> RemoteCallbacks<'a> {
         // Set Authentication method
         let mut remote_callbacks = RemoteCallbacks::new();
         remote_callbacks.credentials(|_, _, _| {
             // TODO: Handle different authentications type (username, password)
             Cred::ssh_key_from_agent("git")
         });
+
+        // Report the progress, the reporter prints its last state when the callbacks are dropped
+        let reporter = Rc::new(RefCell::new(Reporter::new(progress, operation)));
+        let transfer = reporter.clone();
+        remote_callbacks.transfer_progress(move |stats| {
+            transfer.borrow_mut().transfer(stats.received_objects(), stats.total_objects(), stats.received_bytes());
+            true
+        });
+        let sideband = reporter.clone();
+        remote_callbacks.sideband_progress(move |data| {
+            sideband.borrow_mut().sideband(data);
+            true
+        });
+        remote_callbacks.push_transfer_progress(move |current, total, bytes| {
+            reporter.borrow_mut().push(current, total, bytes);
+        });
         remote_callbacks
     }
 
-    pub fn fetch_options<'a>() -> FetchOptions<'a> {
+    pub fn fetch_options<'a>(progress: Progress, operation: &'static str) -> FetchOptions<'a> {
         // Add Authentication call back to fetch options
         let mut fetch_options = FetchOptions::new();
-        fetch_options.remote_callbacks(Self::remote_callbacks());
+        fetch_options.remote_callbacks(Self::remote_callbacks(progress, operation));
         fetch_options
     }
 
-    pub fn push_options<'a>() -> PushOptions<'a> {
+    pub fn push_options<'a>(progress: Progress) -> PushOptions<'a> {
         let mut push_options = PushOptions::new();
-        push_options.remote_callbacks(Self::remote_callbacks());
+        push_options.remote_callbacks(Self::remote_callbacks(progress, "push"));
         push_options
     }
 
-    /// Fetch all the branches of all remotes
-    pub fn fetch(&self) -> Result<(), Error> {
-        let mut fetch_options = Self::fetch_options();
+    /// Fetch all the branches of the passed remote
+    pub fn fetch(&self, remote_name: &str) -> Result<(), Error> {
+        let mut fetch_options = Self::fetch_options(self.progress, "fetch");
 
-        let remotes = self.repository.remotes()?;
-        for remote in remotes.iter() {
-            match remote {
-                Some(remote) => {
-                    let mut remote = self.repository.find_remote(remote)?;
-                    // Fetch all branches
-                    remote.fetch(&[], Some(&mut fetch_options), None)?;
-                }
-                None => {}
-            }
-        }
+        let mut remote = self.repository.find_remote(remote_name)?;
+        // Fetch all branches
+        remote.fetch::<&str>(&[], Some(&mut fetch_options), None)?;
 
         Ok(())
     }
 
     /// Create a new repository
-    pub fn clone(repo: &str, work_dir: &Path) -> Result<(), Error> {
-        let fetch_options = Self::fetch_options();
+    pub fn clone(repo: &str, work_dir: &Path, progress: Progress) -> Result<(), Error> {
+        let fetch_options = Self::fetch_options(progress, "clone");
 
         let mut repo_builder = RepoBuilder::new();
         repo_builder.fetch_options(fetch_options);
@@ -159,14 +261,57 @@ impl Helper {
         Ok(())
     }
 
-    /// Update the local branch with the remote one, only fast forward
-    pub fn pull(&self, branch_name: &str) -> Result<(), Error> {
-        self.fetch()?;
+    /// Name on the passed remote of the branch the local branch pulls from and pushes to: the tracked
+    /// branch if the local branch tracks that remote, otherwise the branch with the same name
+    fn remote_branch_name(&self, branch_name: &str, remote_name: &str) -> Result<String, Error> {
+        let config = self.repository.config()?;
+        let remote = config.get_string(format!("branch.{}.remote", branch_name).as_str());
+        let merge = config.get_string(format!("branch.{}.merge", branch_name).as_str());
+        match (remote, merge) {
+            (Ok(ref remote), Ok(ref merge)) if remote == remote_name => Ok(merge.trim_start_matches("refs/heads/").to_string()),
+            _ => Ok(branch_name.to_string()),
+        }
+    }
+
+    /// Return the remote branch to pull from the passed remote: the upstream if it is on that remote,
+    /// otherwise the branch with the same name, None if it does not exist
+    fn pull_source(&self, branch_name: &str, remote_name: &str) -> Result<Option<Branch<'_>>, Error> {
+        self.repair_upstream(branch_name)?;
+        let remote_branch_name = format!("{}/{}", remote_name, self.remote_branch_name(branch_name, remote_name)?);
+        match self.repository.find_branch(remote_branch_name.as_str(), BranchType::Remote) {
+            Ok(branch) => Ok(Some(branch)),
+            Err(error) => {
+                match error.code() {
+                    ErrorCode::NotFound => Ok(None),
+                    _ => Err(error.into()),
+                }
+            },
+        }
+    }
+
+    /// Fail if the remote branch to pull from has not been fetched, the branch has no upstream
+    /// on the remote and no remote branch with its name
+    pub fn ensure_pull_source(&self, branch_name: &str, remote_name: &str) -> Result<(), Error> {
+        match self.pull_source(branch_name, remote_name)? {
+            Some(_) => Ok(()),
+            None => {
+                let message = format!("the branch does not track any branch of the remote '{}', set it with 'scog remote set'", remote_name);
+                Err(Error::new(Kind::Git, message).with_branch(branch_name))
+            }
+        }
+    }
 
+    /// Update the local branch with the fetched remote one, if the branches diverged and merge is passed
+    /// create a merge commit with that identity, otherwise only fast forward. Return the merge commit
+    pub fn pull(&self, branch_name: &str, remote_name: &str, merge: Option<&Identity>, structured: &[PathBuf]) -> Result<Option<Oid>, Error> {
         let branch = self.find_local_branch(branch_name)?;
         let branch_oid = branch.get().peel_to_commit()?.id();
 
-        let upstream = branch.upstream()?.get().peel_to_commit()?;
+        // Nothing to pull if the branch has never been pushed to the remote
+        let upstream = match self.pull_source(branch_name, remote_name)? {
+            Some(upstream) => upstream.get().peel_to_commit()?,
+            None => return Ok(None),
+        };
         let upstream_oid = upstream.id();
 
         let base = self.repository.merge_base(branch_oid, upstream_oid)?;
@@ -175,12 +320,15 @@ impl Helper {
         // branch points than the local branch is ahead the remote branch and there is nothing
         // to pull
         if base == upstream_oid {
-            return Ok(());
+            return Ok(None);
         }
 
         // Check if it is possible to fast forward
         if base != branch_oid {
-            Err(format!("can not fast forward branch: {}, fix this manually", branch_name))?;
+            if let Some(identity) = merge {
+                return self.merge(branch_name, &upstream, identity, structured).map(Some);
+            }
+            return Err(Error::new(Kind::Conflict, "can not fast forward branch, fix this manually".to_string()).with_branch(branch_name));
         }
 
         // Checkout files of commit
@@ -190,14 +338,229 @@ impl Helper {
         let mut reference = self.repository.find_reference(branch.get().name().unwrap_or_default())?;
         reference.set_target(upstream_oid, "pull: Fast-forward")?;
 
+        Ok(None)
+    }
+
+    /// Merge the commit into the checked out branch, the conflicting files inside the structured paths are
+    /// merged key by key, fail without touching anything on the other conflicts
+    fn merge(&self, branch_name: &str, commit: &Commit, identity: &Identity, structured: &[PathBuf]) -> Result<Oid, Error> {
+        let head = self.repository.head()?.peel_to_commit()?;
+
+        let mut index = self.repository.merge_commits(&head, commit, None)?;
+        if index.has_conflicts() {
+            let unresolved = self.resolve_conflicts(&mut index, structured)?;
+            if !unresolved.is_empty() {
+                let message = format!("can not merge the remote branch, fix this manually: {}", unresolved.join(", "));
+                return Err(Error::new(Kind::Conflict, message).with_branch(branch_name));
+            }
+        }
+
+        // Commit the merged tree on the branch and checkout its files
+        let tree = index.write_tree_to(&self.repository)?;
+        let tree = self.repository.find_tree(tree)?;
+        let message = format!("Merge remote changes into {}", branch_name);
+        let oid = self.create_commit(identity, message.as_str(), &tree, &[&head, commit])?;
+        self.repository.checkout_head(Some(CheckoutBuilder::new().force()))?;
+
+        Ok(oid)
+    }
+
+    /// Merge key by key the conflicting files inside the structured paths and return the files
+    /// still conflicting, with the reason when the key by key merge failed
+    fn resolve_conflicts(&self, index: &mut Index, structured: &[PathBuf]) -> Result<Vec<String>, Error> {
+        let conflicts: Vec<IndexConflict> = index.conflicts()?.collect::<Result<_, _>>()?;
+        let mut unresolved: Vec<String> = vec![];
+        for conflict in conflicts {
+            let (ours, theirs) = match (conflict.our, conflict.their) {
+                (Some(ours), Some(theirs)) => (ours, theirs),
+                (ours, theirs) => {
+                    let entry = ours.or(theirs).or(conflict.ancestor);
+                    let path = entry.map(|entry| String::from_utf8_lossy(entry.path.as_slice()).to_string()).unwrap_or_default();
+                    unresolved.push(path);
+                    continue;
+                }
+            };
+            let path = PathBuf::from(String::from_utf8_lossy(ours.path.as_slice()).to_string());
+            if !structured.iter().any(|structured| path.starts_with(structured)) {
+                unresolved.push(path.to_string_lossy().to_string());
+                continue;
+            }
+
+            let base = match conflict.ancestor {
+                Some(ref ancestor) => self.repository.find_blob(ancestor.id)?.content().to_vec(),
+                None => vec![],
+            };
+            let our_content = self.repository.find_blob(ours.id)?.content().to_vec();
+            let their_content = self.repository.find_blob(theirs.id)?.content().to_vec();
+            match merge::merge(path.as_path(), base.as_slice(), our_content.as_slice(), their_content.as_slice()) {
+                Ok(content) => {
+                    // Replace the conflict with an entry of the merged content
+                    let mut entry = ours;
+                    entry.id = self.repository.blob(content.as_slice())?;
+                    entry.file_size = content.len() as u32;
+                    entry.flags &= !INDEX_STAGE_MASK;
+                    for stage in 1..=3 {
+                        index.remove(path.as_path(), stage)?;
+                    }
+                    index.add(&entry)?;
+                }
+                Err(reason) => unresolved.push(format!("{} ({})", path.to_string_lossy(), reason)),
+            }
+        }
+        Ok(unresolved)
+    }
+
+    /// Return the id of the commit pointed by HEAD
+    pub fn head_oid(&self) -> Result<Oid, Error> {
+        Ok(self.repository.head()?.peel_to_commit()?.id())
+    }
+
+    /// Move the current branch to the passed commit discarding all changes in the work dir
+    pub fn reset_hard(&self, oid: Oid) -> Result<(), Error> {
+        let commit = self.repository.find_commit(oid)?;
+        self.repository.reset(commit.as_object(), ResetType::Hard, None)?;
         Ok(())
     }
 
+    /// Checkout the local branch and move it to the passed commit, discarding any change
+    pub fn reset_branch(&self, branch_name: &str, oid: Oid) -> Result<(), Error> {
+        let branch = self.find_local_branch(branch_name)?;
+        self.repository.set_head(branch.get().name().unwrap_or_default())?;
+        self.reset_hard(oid)
+    }
+
+    /// Return the upstream name of the passed local branch and how many commits the branch is
+    /// ahead and behind it, None if the branch has no upstream
+    pub fn upstream_status(&self, branch_name: &str) -> Result<Option<(String, usize, usize)>, Error> {
+        let branch = self.find_local_branch(branch_name)?;
+        let upstream = match branch.upstream() {
+            Ok(upstream) => upstream,
+            Err(error) => {
+                match error.code() {
+                    ErrorCode::NotFound => return Ok(None),
+                    _ => return Err(error.into()),
+                }
+            },
+        };
+        let upstream_name = upstream.name()?.unwrap_or_default().to_string();
+        let local_oid = branch.get().peel_to_commit()?.id();
+        let upstream_oid = upstream.get().peel_to_commit()?.id();
+        let (ahead, behind) = self.repository.graph_ahead_behind(local_oid, upstream_oid)?;
+        Ok(Some((upstream_name, ahead, behind)))
+    }
+
+    /// Check if the branch the local branch pushes to on any remote contains the commit,
+    /// the remote branches are as fresh as the last fetch or push
+    pub fn remote_contains(&self, branch_name: &str, oid: Oid) -> Result<bool, Error> {
+        for remote in self.repository.remotes()?.iter().flatten() {
+            let remote_branch_name = format!("{}/{}", remote, self.remote_branch_name(branch_name, remote)?);
+            let remote_oid = match self.repository.find_branch(remote_branch_name.as_str(), BranchType::Remote) {
+                Ok(branch) => branch.get().peel_to_commit()?.id(),
+                Err(ref error) if error.code() == ErrorCode::NotFound => continue,
+                Err(error) => return Err(error.into()),
+            };
+            if remote_oid == oid || self.repository.graph_descendant_of(remote_oid, oid)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Return the id of the commit pointed by the passed local branch
+    pub fn branch_oid(&self, branch_name: &str) -> Result<Oid, Error> {
+        Ok(self.find_local_branch(branch_name)?.get().peel_to_commit()?.id())
+    }
+
+    /// Read the content of a file in the tree of the passed commit,
+    /// return None if the file does not exist in that commit
+    pub fn read_file(&self, oid: Oid, path: &Path) -> Result<Option<Vec<u8>>, Error> {
+        let tree = self.repository.find_commit(oid)?.tree()?;
+        let entry = match tree.get_path(path) {
+            Ok(entry) => entry,
+            Err(error) => {
+                match error.code() {
+                    ErrorCode::NotFound => return Ok(None),
+                    _ => return Err(error.into()),
+                }
+            },
+        };
+        match self.repository.find_blob(entry.id()) {
+            Ok(blob) => Ok(Some(blob.content().to_vec())),
+            // The path is a dir
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// List the files of the passed commit inside path, or path itself if it is a file
+    pub fn list_files(&self, oid: Oid, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        let tree = self.repository.find_commit(oid)?.tree()?;
+        let entry = match tree.get_path(path) {
+            Ok(entry) => entry,
+            Err(error) => {
+                match error.code() {
+                    ErrorCode::NotFound => return Ok(vec![]),
+                    _ => return Err(error.into()),
+                }
+            },
+        };
+        if entry.kind() != Some(ObjectType::Tree) {
+            return Ok(vec![path.to_path_buf()]);
+        }
+
+        let mut files: Vec<PathBuf> = vec![];
+        self.repository.find_tree(entry.id())?.walk(TreeWalkMode::PreOrder, |root, entry| {
+            if entry.kind() == Some(ObjectType::Blob) {
+                files.push(path.join(root).join(entry.name().unwrap_or_default()));
+            }
+            TreeWalkResult::Ok
+        })?;
+        Ok(files)
+    }
+
+    /// Return the commit of a revision like a commit id, a branch or HEAD~2
+    pub fn resolve(&self, revision: &str) -> Result<Oid, Error> {
+        match self.repository.revparse_single(revision) {
+            Ok(object) => Ok(object.peel_to_commit()?.id()),
+            Err(error) => {
+                match error.code() {
+                    ErrorCode::NotFound | ErrorCode::Ambiguous | ErrorCode::InvalidSpec => {
+                        Err(Error::new(Kind::Usage, format!("unknown revision '{}'", revision)).with_source(error))
+                    }
+                    _ => Err(error.into()),
+                }
+            },
+        }
+    }
+
+    /// Return the last commit of the branch committed before the passed time, in seconds since epoch
+    pub fn commit_at(&self, branch_name: &str, time: i64) -> Result<Option<Oid>, Error> {
+        let mut revwalk = self.repository.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        revwalk.push(self.branch_oid(branch_name)?)?;
+        for oid in revwalk {
+            let oid = oid?;
+            if self.repository.find_commit(oid)?.time().seconds() <= time {
+                return Ok(Some(oid));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Commit on top of HEAD a tree equal to the tree of the passed commit
+    /// and update the work dir, this reverts all the changes made after that commit
+    pub fn revert_to(&self, oid: Oid, message: &str, identity: &Identity) -> Result<Oid, Error> {
+        let tree = self.repository.find_commit(oid)?.tree()?;
+        let parent = self.repository.head()?.peel_to_commit()?;
+        let commit = self.create_commit(identity, message, &tree, &[&parent])?;
+        self.repository.checkout_head(Some(CheckoutBuilder::new().force()))?;
+        Ok(commit)
+    }
+
     pub fn get_current_branch_name(&self) -> Result<String, Error> {
         let head = self.repository.head()?;
         match head.is_branch() {
             true => Ok(head.shorthand().unwrap_or_default().to_owned()),
-            false => Err("HEAD is not attached to a branch".to_string())?,
+            false => Err(Error::new(Kind::Git, "HEAD is not attached to a branch".to_string())),
         }
     }
 
@@ -209,14 +572,217 @@ impl Helper {
         Ok(())
     }
 
-    /// Commit all staged files with the passed message and the local user
-    pub fn commit(&self, message: &str) -> Result<Oid, Error> {
+    /// Remove the file from the index
+    pub fn remove(&self, path: &Path) -> Result<(), Error> {
+        let mut index = self.repository.index()?;
+        index.remove_path(path)?;
+        index.write()?;
+        Ok(())
+    }
+
+    /// Commit all staged files with the passed message and the local user,
+    /// if HEAD does not point to a commit yet the initial commit is created
+    pub fn commit(&self, message: &str, identity: &Identity) -> Result<Oid, Error> {
         let tree = self.repository.index()?.write_tree()?;
         let tree = self.repository.find_tree(tree)?;
-        let signature = self.repository.signature()?;
-        let parent = self.repository.head()?.peel_to_commit()?;
-        let commit = self.repository.commit(Some("HEAD"), &signature, &signature, message, &tree, &[&parent])?;
-        Ok(commit)
+        let parent = match self.repository.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(error) => {
+                match error.code() {
+                    ErrorCode::UnbornBranch | ErrorCode::NotFound => None,
+                    _ => return Err(error.into()),
+                }
+            },
+        };
+        let parents: Vec<&Commit> = parent.iter().collect();
+        self.create_commit(identity, message, &tree, &parents)
+    }
+
+    /// Signature of the commits: the identity values, otherwise the git user,
+    /// otherwise scog and scog@hostname
+    fn signature(&self, identity: &Identity) -> Result<Signature<'static>, Error> {
+        let config = self.repository.config()?;
+        let name = identity.name.clone()
+            .or_else(|| config.get_string("user.name").ok())
+            .unwrap_or_else(|| "scog".to_string());
+        let email = identity.email.clone()
+            .or_else(|| config.get_string("user.email").ok())
+            .unwrap_or_else(|| format!("scog@{}", host::hostname()));
+        Ok(Signature::now(name.as_str(), email.as_str())?)
+    }
+
+    /// Create a commit and move HEAD to it, if the identity has a key the commit is signed
+    fn create_commit(&self, identity: &Identity, message: &str, tree: &Tree, parents: &[&Commit]) -> Result<Oid, Error> {
+        let signature = self.signature(identity)?;
+        let (format, key) = match identity.signing {
+            Some((format, ref key)) => (format, key.as_str()),
+            None => return Ok(self.repository.commit(Some("HEAD"), &signature, &signature, message, tree, parents)?),
+        };
+
+        // Sign the commit content
+        let content = self.repository.commit_create_buffer(&signature, &signature, message, tree, parents)?;
+        let content = String::from_utf8_lossy(&content).to_string();
+        let signed = sign::sign(format, key, content.as_str())?;
+        let oid = self.repository.commit_signed(content.as_str(), signed.as_str(), Some("gpgsig"))?;
+
+        // Move the branch pointed by HEAD, it can be unborn, or HEAD itself if detached
+        let head = self.repository.find_reference("HEAD")?;
+        match head.symbolic_target() {
+            Some(target) => {
+                self.repository.reference(target, oid, true, message)?;
+            }
+            None => self.repository.set_head_detached(oid)?,
+        }
+        Ok(oid)
+    }
+
+    /// Add a new remote
+    pub fn add_remote(&self, remote_name: &str, url: &str) -> Result<(), Error> {
+        self.repository.remote(remote_name, url)?;
+        Ok(())
+    }
+
+    /// Track the passed branch of the passed remote, it works also if
+    /// the remote branch has not been fetched yet
+    pub fn set_upstream(&self, branch_name: &str, remote_name: &str, remote_branch_name: &str) -> Result<(), Error> {
+        self.repository.find_remote(remote_name)?;
+        let mut config = self.repository.config()?;
+        config.set_str(format!("branch.{}.remote", branch_name).as_str(), remote_name)?;
+        config.set_str(format!("branch.{}.merge", branch_name).as_str(), format!("refs/heads/{}", remote_branch_name).as_str())?;
+        Ok(())
+    }
+
+    /// Return the upstream of the passed local branch in the form REMOTE/BRANCH,
+    /// None if the branch does not track any remote branch
+    pub fn upstream(&self, branch_name: &str) -> Result<Option<String>, Error> {
+        let config = self.repository.config()?;
+        let remote = config.get_string(format!("branch.{}.remote", branch_name).as_str());
+        let merge = config.get_string(format!("branch.{}.merge", branch_name).as_str());
+        match (remote, merge) {
+            (Ok(remote), Ok(merge)) => Ok(Some(format!("{}/{}", remote, merge.trim_start_matches("refs/heads/")))),
+            _ => Ok(None),
+        }
+    }
+
+    /// If the passed local branch does not track any remote branch but a remote branch with the same
+    /// name exists on the default remote, track it. Return true if the branch has an upstream
+    pub fn repair_upstream(&self, branch_name: &str) -> Result<bool, Error> {
+        if self.upstream(branch_name)?.is_some() {
+            return Ok(true);
+        }
+        let remote = match self.default_remote(branch_name)? {
+            Some(remote) => remote,
+            None => return Ok(false),
+        };
+        let remote_branch = format!("{}/{}", remote, branch_name);
+        match self.repository.find_branch(remote_branch.as_str(), BranchType::Remote) {
+            Ok(_) => {
+                self.set_upstream(branch_name, remote.as_str(), branch_name)?;
+                Ok(true)
+            }
+            Err(error) => {
+                match error.code() {
+                    ErrorCode::NotFound => Ok(false),
+                    _ => Err(error.into()),
+                }
+            },
+        }
+    }
+
+    /// Return all the local branch names
+    pub fn local_branch_names(&self) -> Result<Vec<String>, Error> {
+        let mut names: Vec<String> = vec![];
+        for branch in self.repository.branches(Some(BranchType::Local))? {
+            let (branch, _) = branch?;
+            if let Some(name) = branch.name()? {
+                names.push(name.to_string());
+            }
+        }
+        Ok(names)
+    }
+
+    /// Return the staged paths and how they changed compared to HEAD
+    pub fn staged_changes(&self) -> Result<Vec<(PathBuf, Delta)>, Error> {
+        let tree = match self.repository.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(error) => {
+                match error.code() {
+                    ErrorCode::UnbornBranch | ErrorCode::NotFound => None,
+                    _ => return Err(error.into()),
+                }
+            },
+        };
+        let diff = self.repository.diff_tree_to_index(tree.as_ref(), None, None)?;
+        let changes = diff.deltas()
+            .filter_map(|delta| {
+                let path = delta.new_file().path().or_else(|| delta.old_file().path())?;
+                Some((path.to_path_buf(), delta.status()))
+            })
+            .collect();
+        Ok(changes)
+    }
+
+    /// Walk the commits reachable from the passed branches, newest first, and return the ones
+    /// changing the path (all of them if None) committed between since and until
+    pub fn log(&self, branch_names: &[String], path: Option<&Path>, since: Option<i64>, until: Option<i64>, patch: bool) -> Result<Vec<Logged>, Error> {
+        let mut revwalk = self.repository.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        for branch_name in branch_names {
+            revwalk.push(self.branch_oid(branch_name)?)?;
+        }
+
+        let mut logged: Vec<Logged> = vec![];
+        for oid in revwalk {
+            let commit = self.repository.find_commit(oid?)?;
+            let time = commit.time().seconds();
+            if since.is_some_and(|since| time < since) || until.is_some_and(|until| time > until) {
+                continue;
+            }
+
+            // Diff against the first parent, limited to the path
+            let mut diff_options = DiffOptions::new();
+            if let Some(path) = path {
+                diff_options.pathspec(path);
+            }
+            let parent = match commit.parent(0) {
+                Ok(parent) => Some(parent.tree()?),
+                Err(_) => None,
+            };
+            let diff = self.repository.diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), Some(&mut diff_options))?;
+            let files: Vec<PathBuf> = diff.deltas()
+                .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+                .map(|path| path.to_path_buf())
+                .collect();
+            if path.is_some() && files.is_empty() {
+                continue;
+            }
+
+            let patch = match patch {
+                true => {
+                    let mut text = String::new();
+                    diff.print(DiffFormat::Patch, |_, _, line| {
+                        if let '+' | '-' | ' ' = line.origin() {
+                            text.push(line.origin());
+                        }
+                        text.push_str(String::from_utf8_lossy(line.content()).as_ref());
+                        true
+                    })?;
+                    Some(text)
+                }
+                false => None,
+            };
+
+            logged.push(Logged {
+                oid: commit.id(),
+                author: commit.author().name().unwrap_or_default().to_string(),
+                email: commit.author().email().unwrap_or_default().to_string(),
+                time,
+                message: commit.message().unwrap_or_default().to_string(),
+                files,
+                patch,
+            });
+        }
+        Ok(logged)
     }
 
     /// Check if the current work dir is dirty
@@ -226,24 +792,70 @@ impl Helper {
         Ok(diff.deltas().len() > 0)
     }
 
-    /// Push the passed branch to all remotes
-    pub fn push(&self, branch_name: &str) -> Result<(), Error> {
+    /// Push the passed branch to its branch on the passed remote, return the pushed reference in the form REMOTE:REFERENCE
+    pub fn push(&self, branch_name: &str, remote_name: &str) -> Result<String, Error> {
         let branch = self.find_branch(branch_name)?;
 
-        let mut push_options = Self::push_options();
+        let mut push_options = Self::push_options(self.progress);
 
-        let remotes = self.repository.remotes()?;
-        for remote in remotes.iter() {
-            match remote {
-                Some(remote) => {
-                    let mut remote = self.repository.find_remote(remote)?;
-                    // Push only the passed branch all remotes
-                    remote.push(&[branch.get().name().unwrap_or_default()], Some(&mut push_options))?;
-                }
-                None => {}
-            }
-        }
+        let reference = branch.get().name().unwrap_or_default();
+        let remote_reference = format!("refs/heads/{}", self.remote_branch_name(branch_name, remote_name)?);
+        let mut remote = self.repository.find_remote(remote_name)?;
+        // Push only the passed branch
+        remote.push(&[format!("{}:{}", reference, remote_reference).as_str()], Some(&mut push_options))?;
 
-        Ok(())
+        Ok(format!("{}:{}", remote_name, remote_reference))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Helper;
+    use super::Identity;
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+    use std::path::Path;
+    use std::process::Command;
+    use std::process::Stdio;
+    use utils::progress::Progress;
+    use utils::sign::Format;
+
+    #[test]
+    fn test_commit_signed_with_ssh_key() {
+        let dir = env::temp_dir().join(format!("scog_test_sign_{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let key = dir.join("id_ed25519");
+        let status = Command::new("ssh-keygen").args(["-q", "-t", "ed25519", "-N", "", "-f"]).arg(&key).status().unwrap();
+        assert!(status.success());
+
+        let helper = Helper::init(&dir.join("repo"), Progress::Silent).unwrap();
+        fs::write(dir.join("repo/.bashrc"), "alias ll='ls -l'").unwrap();
+        helper.add(Path::new(".bashrc")).unwrap();
+        let identity = Identity {
+            name: Some("scog".to_string()),
+            email: Some("scog@test".to_string()),
+            signing: Some((Format::Ssh, key.to_string_lossy().to_string())),
+        };
+        let oid = helper.commit("Signed commit", &identity).unwrap();
+        assert_eq!(helper.head_oid().unwrap(), oid);
+        let branch_name = helper.get_current_branch_name().unwrap();
+        assert_eq!(helper.branch_oid(branch_name.as_str()).unwrap(), oid);
+
+        // The signature must be valid for the commit content
+        let (signature, content) = helper.repository.extract_signature(&oid, None).unwrap();
+        fs::write(dir.join("commit.sig"), &*signature).unwrap();
+        let mut child = Command::new("ssh-keygen")
+            .args(["-Y", "check-novalidate", "-n", "git", "-s"])
+            .arg(dir.join("commit.sig"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(&content).unwrap();
+        assert!(child.wait().unwrap().success());
+
+        let _ = fs::remove_dir_all(&dir);
     }
 }
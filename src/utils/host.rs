@@ -0,0 +1,89 @@
+use hostname;
+use regex;
+use regex::Regex;
+use std::env;
+use std::fs;
+
+/// Name of this machine, "localhost" if it can not be read
+pub fn hostname() -> String {
+    hostname::get()
+        .ok()
+        .and_then(|name| name.into_string().ok())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// Name of the user running scog, "unknown" if it can not be read
+pub fn user() -> String {
+    env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Operating system, like "linux" or "macos"
+pub fn os() -> &'static str {
+    env::consts::OS
+}
+
+/// ID and ID_LIKE values of /etc/os-release, like ["ubuntu", "debian"], empty if it can not be read
+pub fn distros() -> Vec<String> {
+    fs::read_to_string("/etc/os-release")
+        .map(|content| parse_os_release(content.as_str()))
+        .unwrap_or_default()
+}
+
+fn parse_os_release(content: &str) -> Vec<String> {
+    let mut ids: Vec<String> = vec![];
+    for line in content.lines() {
+        let mut parts = line.splitn(2, '=');
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => (key.trim(), value.trim().trim_matches(|c| c == '"' || c == '\'')),
+            _ => continue,
+        };
+        if key == "ID" || key == "ID_LIKE" {
+            ids.extend(value.split_whitespace().map(|id| id.to_string()));
+        }
+    }
+    ids
+}
+
+/// The executable is in one of the dirs of PATH
+pub fn has_executable(name: &str) -> bool {
+    match env::var_os("PATH") {
+        Some(paths) => env::split_paths(&paths).any(|dir| dir.join(name).is_file()),
+        None => false,
+    }
+}
+
+/// Match the text with a glob pattern where * is any text and ? any character
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            _ => regex.push_str(regex::escape(c.to_string().as_str()).as_str()),
+        }
+    }
+    regex.push('$');
+    Regex::new(regex.as_str()).map(|regex| regex.is_match(text)).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_matches;
+    use super::parse_os_release;
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("laptop-*", "laptop-work"));
+        assert!(glob_matches("web?.example.com", "web1.example.com"));
+        assert!(!glob_matches("web?.example.com", "web12.example.com"));
+        assert!(!glob_matches("laptop", "laptop-work"));
+    }
+
+    #[test]
+    fn test_parse_os_release() {
+        let content = "NAME=\"Ubuntu\"\nID=ubuntu\nID_LIKE=\"debian\"\nVERSION_ID=\"24.04\"\n";
+        assert_eq!(parse_os_release(content), vec!["ubuntu", "debian"]);
+    }
+}
@@ -0,0 +1,328 @@
+use serde_json;
+use serde_yaml;
+use serde_yaml::Mapping;
+use serde_yaml::Value;
+use std::path::Path;
+use toml;
+
+/// File formats merged key by key
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Format {
+    Yaml,
+    Json,
+    Toml,
+    Ini,
+}
+
+impl Format {
+    /// Detect the format from the extension, .gitconfig files are INI
+    fn from_path(path: &Path) -> Option<Format> {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        if name == ".gitconfig" {
+            return Some(Format::Ini);
+        }
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => Some(Format::Yaml),
+            Some("json") => Some(Format::Json),
+            Some("toml") => Some(Format::Toml),
+            Some("ini") | Some("cfg") | Some("gitconfig") => Some(Format::Ini),
+            _ => None,
+        }
+    }
+}
+
+/// Three-way merge of the file key by key, the base is empty if the file has been added on both sides.
+/// Fail with the reason if the format is unknown, a version can not be parsed or the same key changed on both sides
+pub fn merge(path: &Path, base: &[u8], ours: &[u8], theirs: &[u8]) -> Result<Vec<u8>, String> {
+    let format = detect(path)?;
+    let base = parse(format, base)?;
+    let ours = parse(format, ours)?;
+    let theirs = parse(format, theirs)?;
+    match merge_value("", Some(&base), Some(&ours), Some(&theirs))? {
+        Some(merged) => write(format, &merged).map(|content| content.into_bytes()),
+        None => Ok(vec![]),
+    }
+}
+
+/// Deep merge the overrides on top of the content, the keys of the overrides replace the ones of the content
+pub fn overlay(path: &Path, content: &[u8], overrides: &[u8]) -> Result<Vec<u8>, String> {
+    let format = detect(path)?;
+    let mut value = parse(format, content)?;
+    overlay_value(&mut value, parse(format, overrides)?);
+    write(format, &value).map(|content| content.into_bytes())
+}
+
+/// Remove the overrides from the content: each overridden key gets back its value in shared,
+/// or is removed if shared does not have it
+pub fn strip(path: &Path, content: &[u8], overrides: &[u8], shared: &[u8]) -> Result<Vec<u8>, String> {
+    let format = detect(path)?;
+    let mut value = parse(format, content)?;
+    strip_value(&mut value, &parse(format, overrides)?, Some(&parse(format, shared)?));
+    write(format, &value).map(|content| content.into_bytes())
+}
+
+fn overlay_value(value: &mut Value, overrides: Value) {
+    match (value, overrides) {
+        (Value::Mapping(mapping), Value::Mapping(overrides)) => {
+            for (name, overridden) in overrides {
+                match mapping.get_mut(&name) {
+                    Some(existing) => overlay_value(existing, overridden),
+                    None => {
+                        mapping.insert(name, overridden);
+                    }
+                }
+            }
+        }
+        (value, overrides) => *value = overrides,
+    }
+}
+
+fn strip_value(value: &mut Value, overrides: &Value, shared: Option<&Value>) {
+    let (mapping, overrides) = match (value, overrides) {
+        (Value::Mapping(mapping), Value::Mapping(overrides)) => (mapping, overrides),
+        _ => return,
+    };
+    for (name, overridden) in overrides.iter() {
+        let shared = match shared {
+            Some(Value::Mapping(shared)) => shared.get(name),
+            _ => None,
+        };
+        match (mapping.get_mut(name), overridden) {
+            (Some(existing), Value::Mapping(_)) if existing.is_mapping() => strip_value(existing, overridden, shared),
+            (Some(existing), _) => match shared {
+                Some(shared) => *existing = shared.clone(),
+                None => {
+                    mapping.remove(name);
+                }
+            },
+            (None, _) => {}
+        }
+    }
+}
+
+fn detect(path: &Path) -> Result<Format, String> {
+    Format::from_path(path).ok_or_else(|| "the format is not known".to_string())
+}
+
+/// Merge a value changed on both sides, mappings are merged key by key
+fn merge_value(key: &str, base: Option<&Value>, ours: Option<&Value>, theirs: Option<&Value>) -> Result<Option<Value>, String> {
+    if ours == theirs || base == theirs {
+        return Ok(ours.cloned());
+    }
+    if base == ours {
+        return Ok(theirs.cloned());
+    }
+
+    if let (Some(Value::Mapping(ours)), Some(Value::Mapping(theirs))) = (ours, theirs) {
+        let empty = Mapping::new();
+        let base = match base {
+            Some(Value::Mapping(base)) => base,
+            _ => &empty,
+        };
+
+        // Our keys keep their order and the keys added by them follow
+        let mut merged = Mapping::new();
+        for (name, value) in ours.iter() {
+            if let Some(value) = merge_value(child(key, name).as_str(), base.get(name), Some(value), theirs.get(name))? {
+                merged.insert(name.clone(), value);
+            }
+        }
+        for (name, value) in theirs.iter() {
+            if !ours.contains_key(name) {
+                if let Some(value) = merge_value(child(key, name).as_str(), base.get(name), None, Some(value))? {
+                    merged.insert(name.clone(), value);
+                }
+            }
+        }
+        return Ok(Some(Value::Mapping(merged)));
+    }
+
+    match key.is_empty() {
+        true => Err("the file changed on both sides".to_string()),
+        false => Err(format!("the key '{}' changed on both sides", key)),
+    }
+}
+
+/// Dotted name of the key inside its parent
+fn child(parent: &str, name: &Value) -> String {
+    let name = match *name {
+        Value::String(ref name) => name.clone(),
+        _ => serde_yaml::to_string(name).unwrap_or_default().trim_start_matches("---").trim().to_string(),
+    };
+    match parent.is_empty() {
+        true => name,
+        false => format!("{}.{}", parent, name),
+    }
+}
+
+fn parse(format: Format, content: &[u8]) -> Result<Value, String> {
+    let content = String::from_utf8_lossy(content);
+    if content.trim().is_empty() {
+        return Ok(Value::Mapping(Mapping::new()));
+    }
+    let parsed = match format {
+        Format::Yaml => serde_yaml::from_str(&content).map_err(|error| error.to_string()),
+        Format::Json => serde_json::from_str(&content).map_err(|error| error.to_string()),
+        Format::Toml => toml::from_str(&content).map_err(|error| error.to_string()),
+        Format::Ini => Ok(parse_ini(&content)),
+    };
+    parsed.map_err(|error| format!("can not parse a version of the file: {}", error))
+}
+
+fn write(format: Format, value: &Value) -> Result<String, String> {
+    match format {
+        Format::Yaml => serde_yaml::to_string(value).map(|content| format!("{}\n", content)).map_err(|error| error.to_string()),
+        Format::Json => serde_json::to_string_pretty(value).map(|content| format!("{}\n", content)).map_err(|error| error.to_string()),
+        Format::Toml => toml::Value::try_from(value)
+            .and_then(|value| toml::to_string(&value))
+            .map_err(|error| error.to_string()),
+        Format::Ini => Ok(write_ini(value)),
+    }
+}
+
+/// Keys before the first section are at the top level, each section is a mapping of its keys.
+/// Comments are dropped, a key without value, like a git boolean, is null and the values of
+/// a repeated key, like a git `fetch`, are a sequence
+fn parse_ini(content: &str) -> Value {
+    let mut root = Mapping::new();
+    let mut section: Option<Value> = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            let name = Value::String(line[1..line.len() - 1].trim().to_string());
+            if !root.contains_key(&name) {
+                root.insert(name.clone(), Value::Mapping(Mapping::new()));
+            }
+            section = Some(name);
+            continue;
+        }
+        let (key, value) = match line.find('=') {
+            Some(index) => (line[..index].trim(), Value::String(line[index + 1..].trim().to_string())),
+            None => (line, Value::Null),
+        };
+        let key = Value::String(key.to_string());
+        match section {
+            Some(ref name) => {
+                if let Some(Value::Mapping(keys)) = root.get_mut(name) {
+                    insert_ini(keys, key, value);
+                }
+            }
+            None => insert_ini(&mut root, key, value),
+        }
+    }
+    Value::Mapping(root)
+}
+
+/// Insert the value of the key, the values of a key already present are collected in a sequence
+fn insert_ini(keys: &mut Mapping, key: Value, value: Value) {
+    let value = match keys.remove(&key) {
+        Some(Value::Sequence(mut values)) => {
+            values.push(value);
+            Value::Sequence(values)
+        }
+        Some(previous) => Value::Sequence(vec![previous, value]),
+        None => value,
+    };
+    keys.insert(key, value);
+}
+
+fn write_ini(value: &Value) -> String {
+    let line = |key: &Value, value: &Value| -> String {
+        let key = key.as_str().unwrap_or_default();
+        match value.as_str() {
+            Some(value) => format!("{} = {}\n", key, value),
+            None => format!("{}\n", key),
+        }
+    };
+    let lines = |key: &Value, value: &Value, indent: &str| -> String {
+        match *value {
+            Value::Sequence(ref values) => values.iter().map(|value| format!("{}{}", indent, line(key, value))).collect(),
+            _ => format!("{}{}", indent, line(key, value)),
+        }
+    };
+
+    let root = match *value {
+        Value::Mapping(ref root) => root,
+        _ => return String::new(),
+    };
+    let mut content = String::new();
+    for (key, value) in root.iter().filter(|(_, value)| !value.is_mapping()) {
+        content.push_str(lines(key, value, "").as_str());
+    }
+    for (name, keys) in root.iter() {
+        if let Value::Mapping(ref keys) = *keys {
+            content.push_str(format!("[{}]\n", name.as_str().unwrap_or_default()).as_str());
+            for (key, value) in keys.iter() {
+                content.push_str(lines(key, value, "\t").as_str());
+            }
+        }
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge;
+    use super::overlay;
+    use super::strip;
+    use std::path::Path;
+
+    fn merged(name: &str, base: &str, ours: &str, theirs: &str) -> Result<String, String> {
+        merge(Path::new(name), base.as_bytes(), ours.as_bytes(), theirs.as_bytes())
+            .map(|content| String::from_utf8(content).unwrap())
+    }
+
+    #[test]
+    fn test_merge_json() {
+        let base = r#"{"editor.fontSize": 12, "files.autoSave": "off"}"#;
+        let ours = r#"{"editor.fontSize": 14, "files.autoSave": "off"}"#;
+        let theirs = r#"{"editor.fontSize": 12, "files.autoSave": "off", "git.enabled": true}"#;
+        assert_eq!(merged("settings.json", base, ours, theirs).unwrap(),
+                   "{\n  \"editor.fontSize\": 14,\n  \"files.autoSave\": \"off\",\n  \"git.enabled\": true\n}\n");
+
+        let theirs = r#"{"editor.fontSize": 16}"#;
+        assert_eq!(merged("settings.json", base, ours, theirs).err().unwrap(), "the key 'editor.fontSize' changed on both sides");
+    }
+
+    #[test]
+    fn test_merge_yaml_and_toml() {
+        let base = "a:\n  b: 1\n";
+        assert_eq!(merged("config.yml", base, "a:\n  b: 1\n  c: 2\n", "a:\n  b: 3\n").unwrap(), "---\na:\n  b: 3\n  c: 2\n");
+        assert_eq!(merged("config.toml", "", "a = 1\n", "[t]\nb = 2\n").unwrap(), "a = 1\n\n[t]\nb = 2\n");
+        assert!(merged("notes.txt", "", "a", "b").is_err());
+    }
+
+    #[test]
+    fn test_overlay_and_strip() {
+        let shared = "{\"editor\": {\"fontSize\": 12, \"tabSize\": 4}}";
+        let overrides = "{\"editor\": {\"fontSize\": 18}, \"window.zoomLevel\": 2}";
+        let local = String::from_utf8(overlay(Path::new("settings.json"), shared.as_bytes(), overrides.as_bytes()).unwrap()).unwrap();
+        assert_eq!(local, "{\n  \"editor\": {\n    \"fontSize\": 18,\n    \"tabSize\": 4\n  },\n  \"window.zoomLevel\": 2\n}\n");
+
+        let changed = local.replace("\"tabSize\": 4", "\"tabSize\": 2");
+        let stripped = strip(Path::new("settings.json"), changed.as_bytes(), overrides.as_bytes(), shared.as_bytes()).unwrap();
+        assert_eq!(String::from_utf8(stripped).unwrap(), "{\n  \"editor\": {\n    \"fontSize\": 12,\n    \"tabSize\": 2\n  }\n}\n");
+    }
+
+    #[test]
+    fn test_merge_ini() {
+        let base = "[user]\n\tname = Davide\n";
+        let ours = "[user]\n\tname = Davide\n\temail = davide@example.com\n";
+        let theirs = "# aliases\n[user]\n\tname = Davide\n[alias]\n\tst = status\n";
+        assert_eq!(merged(".gitconfig", base, ours, theirs).unwrap(),
+                   "[user]\n\tname = Davide\n\temail = davide@example.com\n[alias]\n\tst = status\n");
+    }
+
+    #[test]
+    fn test_merge_ini_repeated_keys() {
+        let base = "[remote \"origin\"]\n\tfetch = +refs/heads/*:refs/remotes/origin/*\n";
+        let ours = "[remote \"origin\"]\n\tfetch = +refs/heads/*:refs/remotes/origin/*\n\tfetch = +refs/tags/*:refs/tags/*\n";
+        let theirs = "[remote \"origin\"]\n\tfetch = +refs/heads/*:refs/remotes/origin/*\n[include]\n\tpath = ~/.gitconfig.work\n\tpath = ~/.gitconfig.local\n";
+        assert_eq!(merged(".gitconfig", base, ours, theirs).unwrap(),
+                   "[remote \"origin\"]\n\tfetch = +refs/heads/*:refs/remotes/origin/*\n\tfetch = +refs/tags/*:refs/tags/*\n\
+                    [include]\n\tpath = ~/.gitconfig.work\n\tpath = ~/.gitconfig.local\n");
+    }
+}
@@ -1,5 +1,13 @@
 pub mod error;
+pub mod expand;
 pub mod git;
+pub mod merge;
+pub mod host;
 pub mod time;
 pub mod backup;
-pub mod copy;
\ No newline at end of file
+pub mod block;
+pub mod copy;
+pub mod output;
+pub mod progress;
+pub mod sign;
+pub mod transaction;
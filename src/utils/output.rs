@@ -0,0 +1,187 @@
+use core::report::ErrorReport;
+use core::report::Report;
+use serde_json;
+use utils::error::Error;
+
+/// Format used to print the result of a command
+#[derive(Clone, Copy, PartialEq)]
+pub enum Output {
+    /// Human readable logs on stderr, status on stdout
+    Human,
+    /// A single JSON object on stdout, human readable logs are still printed on stderr
+    Json,
+}
+
+/// How much human readable logs are printed on stderr
+#[derive(Clone, Copy, PartialEq)]
+pub enum Verbosity {
+    /// Only errors
+    Quiet,
+    /// Branches, commits and pushed references
+    Normal,
+    /// Also each copied file
+    Verbose,
+}
+
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    command: &'a str,
+    ok: bool,
+    #[serde(flatten)]
+    report: &'a Report,
+    error: Option<ErrorReport>,
+}
+
+/// Print the report and the error of the executed command
+pub fn print(output: Output, verbosity: Verbosity, command: &str, report: &Report, result: &Result<(), Error>) {
+    if verbosity != Verbosity::Quiet {
+        print_logs(verbosity, report);
+    }
+
+    for diagnostic in &report.diagnostics {
+        let mut location = diagnostic.file.to_string_lossy().to_string();
+        if let Some(line) = diagnostic.line {
+            location = format!("{}:{}", location, line);
+        }
+        if let Some(column) = diagnostic.column {
+            location = format!("{}:{}", location, column);
+        }
+        eprintln!("{}: {}", location, diagnostic.message);
+    }
+
+    if let Err(ref error) = *result {
+        eprintln!("scog: {}", error);
+        for cause in error.causes() {
+            eprintln!("  caused by: {}", cause);
+        }
+    }
+
+    match output {
+        Output::Human => {
+            print_status(report);
+            print_tracking(report);
+            print_log(report);
+        }
+        Output::Json => {
+            let json = JsonOutput {
+                command,
+                ok: result.is_ok(),
+                report,
+                error: result.as_ref().err().map(ErrorReport::from),
+            };
+            match serde_json::to_string_pretty(&json) {
+                Ok(json) => println!("{}", json),
+                Err(error) => eprintln!("scog: can not serialize output: {}", error),
+            }
+        }
+    }
+}
+
+fn print_logs(verbosity: Verbosity, report: &Report) {
+    if verbosity == Verbosity::Verbose {
+        for path in &report.copied_to_repository {
+            eprintln!("copied to repository: {}", path.to_string_lossy());
+        }
+    }
+    for path in &report.replaced_local {
+        match report.preview {
+            true => eprintln!("would replace local file: {}", path.to_string_lossy()),
+            false => eprintln!("replaced local file: {}", path.to_string_lossy()),
+        }
+    }
+    for branch in &report.backup_branches {
+        eprintln!("created backup branch: {}", branch);
+    }
+    for commit in &report.commits {
+        eprintln!("committed {} on {}", commit.oid, commit.branch);
+    }
+    if verbosity == Verbosity::Verbose {
+        for path in &report.copied_to_local {
+            eprintln!("copied to local: {}", path.to_string_lossy());
+        }
+    }
+    if let Some(ref oid) = report.restored_from {
+        eprintln!("restored from {}", oid);
+    }
+    for reference in &report.pushed_refs {
+        eprintln!("pushed {}", reference);
+    }
+    for failed in &report.failed_pushes {
+        eprintln!("push to {} failed: {}", failed.remote, failed.error.message);
+    }
+    for branch in &report.queued {
+        eprintln!("queued {}, it will be pushed by the next online push or sync", branch);
+    }
+    if let Some(ref profile) = report.profile {
+        eprintln!("using profile {}", profile);
+    }
+    if let Some(ref branch) = report.branch {
+        eprintln!("on branch {}", branch);
+    }
+}
+
+fn print_tracking(report: &Report) {
+    for tracking in &report.tracking {
+        match tracking.upstream {
+            Some(ref upstream) => println!("{} -> {}", tracking.branch, upstream),
+            None => println!("{} (no upstream)", tracking.branch),
+        }
+    }
+}
+
+fn print_log(report: &Report) {
+    for entry in &report.log {
+        println!("commit {}", entry.oid);
+        println!("Author: {} <{}>", entry.author, entry.email);
+        if let Some(ref host) = entry.host {
+            println!("Host:   {}", host);
+        }
+        println!("Date:   {}", entry.time);
+        println!();
+        println!("    {}", entry.summary);
+        println!();
+        for file in &entry.files {
+            println!("    {}", file.to_string_lossy());
+        }
+        if let Some(ref patch) = entry.patch {
+            println!();
+            print!("{}", patch);
+        }
+        println!();
+    }
+}
+
+fn print_status(report: &Report) {
+    let status = match report.status {
+        Some(ref status) => status,
+        None => return,
+    };
+
+    match status.upstream {
+        Some(ref upstream) => println!("On branch {}, tracking {} (ahead {}, behind {})", status.branch, upstream, status.ahead, status.behind),
+        None => println!("On branch {}, no upstream", status.branch),
+    }
+    if let Some(ref profile) = status.profile {
+        println!("Using profile {}", profile);
+    }
+    if status.dirty {
+        println!("Repository has uncommitted changes");
+    }
+    if status.queued {
+        println!("Branch has commits queued while offline, push them with 'scog sync'");
+    }
+    if status.changes.is_empty() {
+        println!("Local files are in sync with the repository");
+    } else {
+        println!("Local files not in sync with the repository:");
+        for change in &status.changes {
+            println!("    {}: {}", change.state.as_str(), change.path.to_string_lossy());
+        }
+    }
+    if !status.skipped.is_empty() {
+        println!("Skipped sections:");
+        for skipped in &status.skipped {
+            println!("    {}: {}", skipped.path.to_string_lossy(), skipped.reason);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::io;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::time::Duration;
+use std::time::Instant;
+use utils::output::Output;
+use utils::output::Verbosity;
+
+/// How the progress of clone, fetch and push is printed on stderr
+#[derive(Clone, Copy, PartialEq)]
+pub enum Progress {
+    /// Nothing is printed
+    Silent,
+    /// A single line redrawn in place, when stderr is a terminal
+    Bar,
+    /// Throttled lines, when stderr is not a terminal or the output is JSON
+    Lines,
+}
+
+impl Progress {
+    /// Choose how to print the progress from the global options and stderr
+    pub fn detect(output: Output, verbosity: Verbosity) -> Progress {
+        if verbosity == Verbosity::Quiet {
+            Progress::Silent
+        } else if output == Output::Human && io::stderr().is_terminal() {
+            Progress::Bar
+        } else {
+            Progress::Lines
+        }
+    }
+
+    /// Minimum time between two prints
+    fn interval(&self) -> Duration {
+        match *self {
+            Progress::Bar => Duration::from_millis(100),
+            _ => Duration::from_secs(1),
+        }
+    }
+}
+
+/// Print the progress of a single network operation, the last throttled state is printed when dropped
+pub struct Reporter {
+    progress: Progress,
+    operation: &'static str,
+    start: Instant,
+    last: Option<Instant>,
+    /// Last message skipped because of throttling
+    pending: Option<String>,
+    /// The bar is on screen and needs a new line
+    drawn: bool,
+}
+
+impl Reporter {
+    pub fn new(progress: Progress, operation: &'static str) -> Reporter {
+        Reporter {
+            progress,
+            operation,
+            start: Instant::now(),
+            last: None,
+            pending: None,
+            drawn: false,
+        }
+    }
+
+    /// Objects and bytes received by clone and fetch
+    pub fn transfer(&mut self, received: usize, total: usize, bytes: usize) {
+        let message = format!("{}: received {}/{} objects ({}%), {}, {}/s",
+                              self.operation, received, total, percent(received, total), size(bytes), size(self.rate(bytes)));
+        self.show(message);
+    }
+
+    /// Objects and bytes sent by push
+    pub fn push(&mut self, current: usize, total: usize, bytes: usize) {
+        let message = format!("{}: sent {}/{} objects ({}%), {}, {}/s",
+                              self.operation, current, total, percent(current, total), size(bytes), size(self.rate(bytes)));
+        self.show(message);
+    }
+
+    /// Messages printed by the remote, like counting and compressing objects
+    pub fn sideband(&mut self, data: &[u8]) {
+        for line in String::from_utf8_lossy(data).split(['\r', '\n']) {
+            let line = line.trim();
+            if !line.is_empty() {
+                self.show(format!("remote: {}", line));
+            }
+        }
+    }
+
+    /// Bytes per second since the operation started
+    fn rate(&self, bytes: usize) -> usize {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        match elapsed > 0.0 {
+            true => (bytes as f64 / elapsed) as usize,
+            false => 0,
+        }
+    }
+
+    fn show(&mut self, message: String) {
+        if self.progress == Progress::Silent {
+            return;
+        }
+
+        // Skip the message if the last one has been printed too recently
+        let now = Instant::now();
+        if let Some(last) = self.last {
+            if now.duration_since(last) < self.progress.interval() {
+                self.pending = Some(message);
+                return;
+            }
+        }
+
+        self.print(message.as_str());
+        self.last = Some(now);
+    }
+
+    fn print(&mut self, message: &str) {
+        let mut stderr = io::stderr();
+        match self.progress {
+            Progress::Bar => {
+                let _ = write!(stderr, "\r{}\x1b[K", message);
+                let _ = stderr.flush();
+                self.drawn = true;
+            }
+            Progress::Lines => {
+                let _ = writeln!(stderr, "{}", message);
+            }
+            Progress::Silent => {}
+        }
+        self.pending = None;
+    }
+}
+
+impl Drop for Reporter {
+    fn drop(&mut self) {
+        if let Some(message) = self.pending.take() {
+            self.print(message.as_str());
+        }
+        if self.drawn {
+            eprintln!();
+        }
+    }
+}
+
+fn percent(current: usize, total: usize) -> usize {
+    match total {
+        0 => 100,
+        _ => current * 100 / total,
+    }
+}
+
+/// Human readable size in binary units
+fn size(bytes: usize) -> String {
+    let units = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} {}", bytes, units[0]),
+        _ => format!("{:.1} {}", size, units[unit]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::percent;
+    use super::size;
+
+    #[test]
+    fn test_size_and_percent() {
+        assert_eq!(size(512), "512 B");
+        assert_eq!(size(1536), "1.5 KiB");
+        assert_eq!(size(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(percent(1, 3), 33);
+        assert_eq!(percent(0, 0), 100);
+    }
+}
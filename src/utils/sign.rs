@@ -0,0 +1,43 @@
+use std::io::Write;
+use std::process::Command;
+use std::process::Stdio;
+use utils::error::Error;
+use utils::error::Kind;
+
+/// Kind of key used to sign the commits
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// A GPG key id, the signature is created by gpg
+    Gpg,
+    /// The path of an SSH private key, the signature is created by ssh-keygen
+    Ssh,
+}
+
+/// Sign the content with the key and return the armored signature
+pub fn sign(format: Format, key: &str, content: &str) -> Result<String, Error> {
+    let (program, args) = match format {
+        Format::Gpg => ("gpg", vec!["--detach-sign", "--armor", "--local-user", key]),
+        Format::Ssh => ("ssh-keygen", vec!["-Y", "sign", "-n", "git", "-f", key]),
+    };
+
+    let mut child = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::new(Kind::Git, format!("can not run {} to sign the commit", program)).with_source(e))?;
+
+    // Write the content and close stdin so that the signer terminates
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(content.as_bytes())?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let message = format!("{} failed to sign the commit: {}", program, String::from_utf8_lossy(&output.stderr).trim());
+        return Err(Error::new(Kind::Git, message));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
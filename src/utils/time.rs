@@ -1,5 +1,8 @@
 use chrono::Local;
 use chrono::DateTime;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::TimeZone;
 
 pub fn now() -> DateTime<Local> {
     Local::now()
@@ -7,4 +10,40 @@ pub fn now() -> DateTime<Local> {
 
 pub fn now_to_string() -> String {
     now().format("%F_%H-%M-%S_%f").to_string()
-}
\ No newline at end of file
+}
+
+/// Parse an RFC 3339 date or a local date like 2026-10-18, 2026-10-18 10:00 or 2026-10-18 10:00:00
+pub fn parse(date: &str) -> Option<DateTime<Local>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Some(date.with_timezone(&Local));
+    }
+    let naive = ["%F %T", "%F %R", "%FT%T", "%FT%R"].iter()
+        .filter_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+        .next()
+        .or_else(|| NaiveDate::parse_from_str(date, "%F").ok().and_then(|date| date.and_hms_opt(0, 0, 0)))?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+/// Format seconds since epoch as a local date
+pub fn format_timestamp(seconds: i64) -> String {
+    match Local.timestamp_opt(seconds, 0).single() {
+        Some(date) => date.format("%F %T %z").to_string(),
+        None => seconds.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use chrono::Local;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse() {
+        let expected = Local.with_ymd_and_hms(2026, 10, 18, 0, 0, 0).unwrap();
+        assert_eq!(parse("2026-10-18"), Some(expected));
+        assert_eq!(parse("2026-10-18 10:30").unwrap().timestamp(), expected.timestamp() + 37800);
+        assert_eq!(parse("2026-10-18T10:30:00Z").unwrap().timestamp(), 1_792_319_400);
+        assert!(parse("yesterday").is_none());
+    }
+}
@@ -0,0 +1,166 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use utils::copy::copy_file;
+use utils::copy::Operation;
+use utils::error::Error;
+
+/// State of a path before the transaction touched it
+enum Snapshot {
+    /// The file existed with this content and permissions
+    File(Vec<u8>, fs::Permissions),
+    /// The file did not exist
+    Missing,
+    /// The dir did not exist and has been created by the transaction
+    CreatedDir,
+}
+
+/// Apply a list of file writes as a single unit, if one of them fails
+/// all the files already written are restored to their previous state
+pub struct Transaction {
+    touched: Vec<(PathBuf, Snapshot)>,
+}
+
+impl Transaction {
+    pub fn new() -> Transaction {
+        Transaction { touched: vec![] }
+    }
+
+    /// Copy all planned files, write the contents and return the written destinations,
+    /// on failure roll back and return an error listing what was rolled back
+    pub fn apply(self, operations: &[Operation], writes: &[(PathBuf, Vec<u8>)]) -> Result<Vec<PathBuf>, Error> {
+        self.execute(|transaction| {
+            let mut copied: Vec<PathBuf> = vec![];
+            for (source, destination) in operations {
+                transaction.copy(source, destination)?;
+                copied.push(destination.clone());
+            }
+            for (destination, content) in writes {
+                transaction.write(destination, content.as_slice())?;
+                copied.push(destination.clone());
+            }
+            Ok(copied)
+        })
+    }
+
+    /// Run all the writes done by f, if f fails roll back and return an error listing what was rolled back
+    /// caused by the error of f
+    pub fn execute<F, T>(mut self, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut Transaction) -> Result<T, Error> {
+        match f(&mut self) {
+            Ok(result) => Ok(result),
+            Err(error) => {
+                let report = self.rollback();
+                let message = format!("rolled back {} path(s):\n{}", report.len(), report.join("\n"));
+                Err(Error::new(error.kind(), message).with_source(error))
+            }
+        }
+    }
+
+    /// Copy source to destination remembering the previous destination state
+    pub fn copy(&mut self, source: &Path, destination: &Path) -> Result<(), Error> {
+        self.snapshot(destination)?;
+        copy_file(source, destination)
+    }
+
+    /// Write content to destination remembering the previous destination state
+    pub fn write(&mut self, destination: &Path, content: &[u8]) -> Result<(), Error> {
+        self.snapshot(destination)?;
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(destination, content)?;
+        Ok(())
+    }
+
+    /// Remove destination remembering its previous state
+    pub fn remove(&mut self, destination: &Path) -> Result<(), Error> {
+        if destination.is_file() {
+            self.snapshot(destination)?;
+            fs::remove_file(destination)?;
+        }
+        Ok(())
+    }
+
+    fn snapshot(&mut self, destination: &Path) -> Result<(), Error> {
+        // Remember the first missing parent because it will be created by the write
+        if let Some(parent) = destination.parent() {
+            let mut created = None;
+            for ancestor in parent.ancestors() {
+                if ancestor.exists() {
+                    break;
+                }
+                created = Some(ancestor.to_path_buf());
+            }
+            if let Some(created) = created {
+                self.touched.push((created, Snapshot::CreatedDir));
+            }
+        }
+
+        let snapshot = if destination.is_file() {
+            Snapshot::File(fs::read(destination)?, fs::metadata(destination)?.permissions())
+        } else {
+            Snapshot::Missing
+        };
+        self.touched.push((destination.to_path_buf(), snapshot));
+
+        Ok(())
+    }
+
+    /// Restore all touched paths in reverse order and return a line for each of them
+    fn rollback(self) -> Vec<String> {
+        let mut report: Vec<String> = vec![];
+        for (path, snapshot) in self.touched.into_iter().rev() {
+            let (action, result) = match snapshot {
+                Snapshot::File(content, permissions) => (
+                    "restored",
+                    fs::write(&path, content).and_then(|_| fs::set_permissions(&path, permissions)),
+                ),
+                // Nothing has been written if the copy failed before creating the file
+                Snapshot::Missing if !path.exists() => continue,
+                Snapshot::Missing => ("removed", fs::remove_file(&path)),
+                Snapshot::CreatedDir => ("removed dir", fs::remove_dir_all(&path)),
+            };
+            match result {
+                Ok(_) => report.push(format!("  {} '{}'", action, path.to_string_lossy())),
+                Err(e) => report.push(format!("  failed to restore '{}': {}", path.to_string_lossy(), e)),
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transaction;
+    use utils::error::Kind;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_rollback() {
+        let dir = env::temp_dir().join(format!("scog_test_transaction_{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("repo")).unwrap();
+        fs::create_dir_all(dir.join("home")).unwrap();
+        fs::write(dir.join("repo/a"), "new a").unwrap();
+        fs::write(dir.join("repo/b"), "new b").unwrap();
+        fs::write(dir.join("home/a"), "old a").unwrap();
+
+        let operations: Vec<(PathBuf, PathBuf)> = vec![
+            (dir.join("repo/a"), dir.join("home/a")),
+            (dir.join("repo/b"), dir.join("home/sub/b")),
+            (dir.join("repo/missing"), dir.join("home/c")),
+        ];
+        let error = Transaction::new().apply(&operations, &[]).err().unwrap();
+
+        assert!(error.to_string().starts_with("rolled back 3 path(s)"));
+        assert_eq!(error.kind(), Kind::Io);
+        assert_eq!(fs::read_to_string(dir.join("home/a")).unwrap(), "old a");
+        assert!(!dir.join("home/sub").exists());
+        assert!(!dir.join("home/c").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}